@@ -0,0 +1,929 @@
+//! Game board model shared by every supported version of the game.
+
+use crate::solver::Deductions;
+use crate::theme;
+
+use anyhow::{ensure, Result};
+use colored::*;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer};
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt::{self, Display},
+    hash::{Hash, Hasher},
+};
+
+/// State of a single cell of the game board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cell {
+    /// Cell not revealed yet.
+    Hidden,
+    /// Revealed cell without adjacent mines.
+    Empty,
+    /// Revealed cell with the given number of adjacent mines.
+    Number(u8),
+    /// Hidden cell flagged by the player.
+    Flag,
+    /// Hidden cell marked with a question mark by the player.
+    Question,
+    /// Cell holding a mine.
+    Mine,
+    /// Cell whose state could not be determined.
+    Unknown,
+}
+
+impl Cell {
+    /// Plain glyph of the cell, telling every state apart without relying on
+    /// colors.
+    pub fn glyph(&self) -> char {
+        match self {
+            Cell::Hidden => '.',
+            Cell::Empty => ' ',
+            Cell::Number(n) => char::from_digit(u32::from(*n), 10).unwrap_or('!'),
+            Cell::Flag => 'F',
+            Cell::Question => '?',
+            Cell::Mine => '*',
+            Cell::Unknown => '!',
+        }
+    }
+
+    /// Glyph of the cell, styled by the current [`Theme`](crate::Theme), which
+    /// by default colors it the way the game itself does.
+    fn colored(&self) -> ColoredString {
+        theme::current()
+            .style(self)
+            .apply(&self.glyph().to_string())
+    }
+
+    /// Stable tag identifying the state of the cell in machine-readable
+    /// output: `hidden`, `empty`, `1` to `8`, `flag`, `question`, `mine` or
+    /// `unknown`.
+    pub fn tag(&self) -> &'static str {
+        const NUMBERS: [&str; 9] = ["0", "1", "2", "3", "4", "5", "6", "7", "8"];
+        match self {
+            Cell::Hidden => "hidden",
+            Cell::Empty => "empty",
+            Cell::Number(n) => NUMBERS.get(*n as usize).copied().unwrap_or("unknown"),
+            Cell::Flag => "flag",
+            Cell::Question => "question",
+            Cell::Mine => "mine",
+            Cell::Unknown => "unknown",
+        }
+    }
+
+    /// Short explanation of the meaning of the cell, used by legends.
+    fn description(&self) -> &'static str {
+        match self {
+            Cell::Hidden => "not opened yet",
+            Cell::Empty => "opened, no adjacent mines",
+            Cell::Number(_) => "opened, number of adjacent mines",
+            Cell::Flag => "flagged",
+            Cell::Question => "marked as unsure",
+            Cell::Mine => "mine",
+            Cell::Unknown => "unrecognized cell value",
+        }
+    }
+}
+
+impl Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.colored())
+    }
+}
+
+/// Cells are serialized as their [`Cell::tag`], the same as in
+/// [`BoardState::to_json`].
+#[cfg(feature = "serde")]
+impl Serialize for Cell {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.tag())
+    }
+}
+
+/// Snapshot of the overall state of a [`Board`], as returned by
+/// [`Board::summary`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardSummary {
+    /// Number of rows of the board.
+    pub rows: usize,
+    /// Number of columns of the board.
+    pub columns: usize,
+    /// Total number of mines.
+    pub mines: u32,
+    /// Mines left to flag, as shown by the game counter. Negative if more
+    /// flags than mines have been placed.
+    pub mines_remaining: i64,
+    /// Number of safe cells opened by the player, that is, empty and numbered
+    /// ones. Mines shown once a game is lost are not included, so a game is
+    /// won once `revealed + mines` is the number of cells of the board.
+    pub revealed: usize,
+    /// Number of cells not opened by the player, including flagged and marked
+    /// ones, as well as the mines shown once a game is lost. Every cell is
+    /// either revealed or hidden, except for the unrecognized ones
+    /// ([`Cell::Unknown`]).
+    pub hidden: usize,
+    /// Number of cells flagged by the player.
+    pub flagged: usize,
+    /// Difficulty preset matching the board, see [`Board::difficulty`].
+    pub difficulty: Difficulty,
+    /// Progress of the game, if the game version exposes it.
+    pub status: Option<GameStatus>,
+    /// Number of seconds shown by the game timer, if the game version exposes
+    /// it, see [`Board::elapsed_secs`].
+    pub elapsed_secs: Option<u32>,
+}
+
+/// Changes between two reads of a board, as returned by [`Board::diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BoardDiff {
+    /// Coordinates of the cells that changed, sorted by row and then by
+    /// column. Empty if the boards are the same.
+    Cells(Vec<(usize, usize)>),
+    /// The boards have different dimensions, so their cells cannot be
+    /// compared.
+    DimensionsChanged,
+}
+
+/// Owned, presentation-free copy of the state of a [`Board`], as returned by
+/// [`Board::to_state`]. Meant to be exported to other tools, see
+/// [`BoardState::to_json`]. With the `serde` feature, it implements
+/// `Serialize` as well, producing the same fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct BoardState {
+    /// Number of rows of the board.
+    pub rows: usize,
+    /// Number of columns of the board.
+    pub columns: usize,
+    /// Total number of mines.
+    pub mines: u32,
+    /// Difficulty preset matching the board.
+    pub difficulty: Difficulty,
+    /// Cells of the board, as a list of rows.
+    pub cells: Vec<Vec<Cell>>,
+}
+
+impl BoardState {
+    /// Serializes the state as a JSON object with the `rows`, `columns`,
+    /// `mines`, `difficulty` and `cells` fields. Cells are given by their
+    /// [`Cell::tag`], so every state maps to a distinct string.
+    pub fn to_json(&self) -> String {
+        let rows: Vec<String> = self
+            .cells
+            .iter()
+            .map(|row| {
+                let cells: Vec<String> = row
+                    .iter()
+                    .map(|cell| format!("\"{}\"", cell.tag()))
+                    .collect();
+                format!("[{}]", cells.join(","))
+            })
+            .collect();
+        format!(
+            "{{\"rows\":{},\"columns\":{},\"mines\":{},\"difficulty\":\"{}\",\"cells\":[{}]}}",
+            self.rows,
+            self.columns,
+            self.mines,
+            self.difficulty.to_string().to_lowercase(),
+            rows.join(",")
+        )
+    }
+
+    /// Serializes the cells as CSV, one line per row of the board with the
+    /// [`Cell::tag`] of each of its cells. There is no header line, since the
+    /// columns are the ones of the board.
+    pub fn to_csv(&self) -> String {
+        self.cells
+            .iter()
+            .map(|row| {
+                let cells: Vec<&str> = row.iter().map(Cell::tag).collect();
+                cells.join(",") + "\n"
+            })
+            .collect()
+    }
+}
+
+/// Game options affecting which cells can appear on the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GameOptions {
+    /// Whether the game plays sounds.
+    pub sound: bool,
+    /// Whether cells can be marked with a question mark.
+    pub marks: bool,
+}
+
+impl Display for GameOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = |enabled| if enabled { "enabled" } else { "disabled" };
+        write!(
+            f,
+            "Marks: {}, Sound: {}",
+            state(self.marks),
+            state(self.sound)
+        )
+    }
+}
+
+/// Difficulty level of a game, as named by the game presets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize), serde(rename_all = "lowercase"))]
+pub enum Difficulty {
+    /// 9 by 9 cells with 10 mines.
+    Beginner,
+    /// 16 by 16 cells with 40 mines.
+    Intermediate,
+    /// 16 rows by 30 columns with 99 mines.
+    Expert,
+    /// Any other board, set up through the custom game options.
+    Custom,
+}
+
+impl Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let difficulty = match self {
+            Difficulty::Beginner => "Beginner",
+            Difficulty::Intermediate => "Intermediate",
+            Difficulty::Expert => "Expert",
+            Difficulty::Custom => "Custom",
+        };
+        write!(f, "{}", difficulty)
+    }
+}
+
+/// Progress of the game, as tracked by the game itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameStatus {
+    /// The game is in progress, or waiting for the first click.
+    Playing,
+    /// Every cell without a mine was opened.
+    Won,
+    /// A mine was opened.
+    Lost,
+    /// The state of the game could not be determined.
+    Unknown,
+}
+
+impl Display for GameStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            GameStatus::Playing => "Playing",
+            GameStatus::Won => "Won",
+            GameStatus::Lost => "Lost",
+            GameStatus::Unknown => "Unknown",
+        };
+        write!(f, "Status: {}", status)
+    }
+}
+
+/// State of the game timer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Timer {
+    /// No cell has been opened yet, so the timer has not started counting.
+    NotStarted,
+    /// The game is in progress, with the given number of seconds elapsed.
+    Running(u32),
+    /// The timer is frozen at the given number of seconds, either because the
+    /// game is over or because it has been paused.
+    Stopped(u32),
+}
+
+impl Display for Timer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Timer::NotStarted => write!(f, "Timer: not started"),
+            Timer::Running(seconds) => write!(f, "Elapsed: {seconds}s"),
+            Timer::Stopped(seconds) => write!(f, "Elapsed: {seconds}s (stopped)"),
+        }
+    }
+}
+
+/// Abstract representation of a Minesweeper game board, meant to be used
+/// for displaying the game state to the user or inspecting it programmatically.
+pub struct Board {
+    pub(crate) mines: u32,
+    pub(crate) rows: usize,
+    pub(crate) columns: usize,
+    pub(crate) options: Option<GameOptions>,
+    pub(crate) timer: Option<Timer>,
+    pub(crate) mine_counter: Option<i32>,
+    pub(crate) status: Option<GameStatus>,
+    data: Vec<Vec<Cell>>,
+}
+
+impl Board {
+    pub(crate) fn new(rows: usize, columns: usize, mines: u32) -> Board {
+        Board {
+            mines,
+            rows,
+            columns,
+            options: None,
+            timer: None,
+            mine_counter: None,
+            status: None,
+            data: vec![vec![Cell::Hidden; columns]; rows],
+        }
+    }
+
+    /// Builds a board from its cells, given as a list of rows of equal length.
+    pub fn from_cells(cells: Vec<Vec<Cell>>, mines: u32) -> Result<Board> {
+        let rows = cells.len();
+        let columns = cells.first().map_or(0, Vec::len);
+        ensure!(
+            cells.iter().all(|row| row.len() == columns),
+            "all rows must have {} columns",
+            columns
+        );
+        Ok(Board {
+            mines,
+            rows,
+            columns,
+            options: None,
+            timer: None,
+            mine_counter: None,
+            status: None,
+            data: cells,
+        })
+    }
+
+    /// Builds a board from rows of [`Cell::glyph`]s, which is handier than
+    /// listing cells for boards written by hand. Unrecognized glyphs are read
+    /// as [`Cell::Unknown`].
+    pub(crate) fn from_glyphs(rows: &[&str], mines: u32) -> Result<Board> {
+        let cells = rows
+            .iter()
+            .map(|row| {
+                row.chars()
+                    .map(|glyph| match glyph {
+                        ' ' => Cell::Empty,
+                        '.' => Cell::Hidden,
+                        'F' => Cell::Flag,
+                        '?' => Cell::Question,
+                        '*' => Cell::Mine,
+                        '1'..='8' => Cell::Number(glyph as u8 - b'0'),
+                        _ => Cell::Unknown,
+                    })
+                    .collect()
+            })
+            .collect();
+        Board::from_cells(cells, mines)
+    }
+
+    /// Returns the number of rows of the board.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the number of columns of the board.
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Returns the total number of mines of the game, flagged or not.
+    pub fn mines(&self) -> u32 {
+        self.mines
+    }
+
+    /// Returns the cell at the given position, or `None` if it lies outside
+    /// the board.
+    pub fn cell(&self, row: usize, column: usize) -> Option<&Cell> {
+        self.data.get(row)?.get(column)
+    }
+
+    /// Returns the cells of the board, as a list of rows.
+    pub fn cells(&self) -> &[Vec<Cell>] {
+        &self.data
+    }
+
+    /// Iterates over the cells of the board along with their `(row, column)`
+    /// coordinates, row by row.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, &Cell)> {
+        self.data
+            .iter()
+            .enumerate()
+            .flat_map(|(r, row)| row.iter().enumerate().map(move |(c, cell)| (r, c, cell)))
+    }
+
+    /// Returns the game options, if the game version exposes them.
+    pub fn options(&self) -> Option<GameOptions> {
+        self.options
+    }
+
+    /// Returns the state of the game timer, if the game version exposes it.
+    pub fn timer(&self) -> Option<Timer> {
+        self.timer
+    }
+
+    /// Returns the number of seconds shown by the game timer, if the game
+    /// version exposes it. Only the Windows XP version does so far, as the
+    /// timer of the Windows 7 one has not been located in its structures.
+    pub fn elapsed_secs(&self) -> Option<u32> {
+        match self.timer? {
+            Timer::NotStarted => Some(0),
+            Timer::Running(seconds) | Timer::Stopped(seconds) => Some(seconds),
+        }
+    }
+
+    /// Returns whether the game is in progress, won or lost, if the game
+    /// version exposes it.
+    pub fn status(&self) -> Option<GameStatus> {
+        self.status
+    }
+
+    /// Returns the value shown by the mine counter of the game, if the game
+    /// version exposes it. Negative if more flags than mines were placed.
+    pub fn mine_counter(&self) -> Option<i32> {
+        self.mine_counter
+    }
+
+    /// Classifies the board into one of the difficulty presets of the game.
+    /// Custom games that happen to match a preset are reported as that preset,
+    /// since both are indistinguishable.
+    pub fn difficulty(&self) -> Difficulty {
+        match (self.rows, self.columns, self.mines) {
+            (9, 9, 10) => Difficulty::Beginner,
+            (16, 16, 40) => Difficulty::Intermediate,
+            (16, 30, 99) => Difficulty::Expert,
+            _ => Difficulty::Custom,
+        }
+    }
+
+    /// Returns a snapshot bundling the dimensions, cell counts and game state
+    /// of the board.
+    pub fn summary(&self) -> BoardSummary {
+        let cells = || self.data.iter().flatten();
+        let flagged = cells().filter(|cell| **cell == Cell::Flag).count();
+        BoardSummary {
+            rows: self.rows,
+            columns: self.columns,
+            mines: self.mines,
+            mines_remaining: self
+                .mine_counter
+                .map_or(self.mines as i64 - flagged as i64, i64::from),
+            revealed: cells()
+                .filter(|cell| matches!(cell, Cell::Empty | Cell::Number(_)))
+                .count(),
+            hidden: cells()
+                .filter(|cell| {
+                    matches!(
+                        cell,
+                        Cell::Hidden | Cell::Flag | Cell::Question | Cell::Mine
+                    )
+                })
+                .count(),
+            flagged,
+            difficulty: self.difficulty(),
+            status: self.status,
+            elapsed_secs: self.elapsed_secs(),
+        }
+    }
+
+    /// Renders the board with the same layout as its [`Display`] output, but
+    /// using the plain [`Cell::glyph`] of each cell, without any colors. The
+    /// [`Display`] output already drops colors when the `NO_COLOR` environment
+    /// variable is set or the standard output is not a terminal; this renders
+    /// plain text regardless.
+    pub fn render_plain(&self) -> String {
+        let mut rendered = String::new();
+        for row in &self.data {
+            rendered.push('\t');
+            for cell in row {
+                rendered.push(cell.glyph());
+                rendered.push(' ');
+            }
+            rendered.push('\n');
+        }
+        rendered
+    }
+
+    /// Renders the board followed by a legend explaining each of its glyphs,
+    /// so that the output can be understood without knowing the notation.
+    pub fn render_with_legend(&self) -> String {
+        const LEGEND: [Cell; 7] = [
+            Cell::Hidden,
+            Cell::Empty,
+            Cell::Number(1),
+            Cell::Flag,
+            Cell::Question,
+            Cell::Mine,
+            Cell::Unknown,
+        ];
+        let mut rendered = format!("{self}\nLegend:\n");
+        for cell in LEGEND {
+            let glyph = match cell {
+                Cell::Number(_) => format!("{}-{}", Cell::Number(1), Cell::Number(8)),
+                // Quoted so that the blank glyph can be told apart
+                Cell::Empty => format!("'{cell}'"),
+                _ => cell.to_string(),
+            };
+            rendered += &format!("\t{glyph}\t{}\n", cell.description());
+        }
+        rendered
+    }
+
+    /// Deduces which hidden cells are safe and which hold a mine, see
+    /// [`solver::solve`](crate::solver::solve).
+    pub fn solve(&self) -> Deductions {
+        crate::solver::solve(self)
+    }
+
+    /// Renders the board highlighting the given deductions: cells that can be
+    /// safely opened are shown over a green background, and cells holding a
+    /// mine over a red one. Without colors, they are shown as `o` and `x`
+    /// respectively instead.
+    pub fn render_with_deductions(&self, deductions: &Deductions) -> String {
+        let colorize = colored::control::SHOULD_COLORIZE.should_colorize();
+        let mut rendered = String::new();
+        for (r, row) in self.data.iter().enumerate() {
+            rendered.push('\t');
+            for (c, cell) in row.iter().enumerate() {
+                let glyph = cell.colored();
+                let glyph = match (
+                    deductions.safe.contains(&(r, c)),
+                    deductions.mines.contains(&(r, c)),
+                ) {
+                    (true, _) if colorize => glyph.on_green(),
+                    (true, _) => "o".into(),
+                    (_, true) if colorize => glyph.on_red(),
+                    (_, true) => "x".into(),
+                    _ => glyph,
+                };
+                rendered += &format!("{glyph} ");
+            }
+            rendered.push('\n');
+        }
+        rendered
+    }
+
+    /// Renders the board emphasizing the given cells, typically the ones
+    /// returned by [`Board::diff`]: they are underlined, or followed by a `^`
+    /// when colors are off, a mark no [`Cell::glyph`] uses.
+    pub fn render_with_changes(&self, changed: &[(usize, usize)]) -> String {
+        let colorize = colored::control::SHOULD_COLORIZE.should_colorize();
+        let mut rendered = String::new();
+        for (r, row) in self.data.iter().enumerate() {
+            rendered.push('\t');
+            for (c, cell) in row.iter().enumerate() {
+                let glyph = cell.colored();
+                rendered += &match changed.contains(&(r, c)) {
+                    true if colorize => format!("{} ", glyph.underline()),
+                    true => format!("{glyph}^"),
+                    false => format!("{glyph} "),
+                };
+            }
+            rendered.push('\n');
+        }
+        rendered
+    }
+
+    /// Compares the cells of the board with the ones of a previous read of
+    /// it. The cells themselves are compared, regardless of how they are
+    /// rendered. Boards of different dimensions, such as the ones of a new
+    /// game of another difficulty, are reported as such instead.
+    pub fn diff(&self, previous: &Board) -> BoardDiff {
+        if (self.rows, self.columns) != (previous.rows, previous.columns) {
+            return BoardDiff::DimensionsChanged;
+        }
+        BoardDiff::Cells(
+            self.iter()
+                .filter(|&(r, c, cell)| previous.cell(r, c) != Some(cell))
+                .map(|(r, c, _)| (r, c))
+                .collect(),
+        )
+    }
+
+    /// Renders a compact mini-map of the board, where each braille character
+    /// stands for a block of 2 columns by 4 rows. Raised dots mark the cells
+    /// opened by the player, giving a quick overview of the game progress.
+    pub fn render_braille(&self) -> String {
+        // Dot bits of a braille pattern, indexed by row and column in the block
+        const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+        let mut rendered = String::new();
+        for block_row in (0..self.rows).step_by(DOTS.len()) {
+            for block_column in (0..self.columns).step_by(DOTS[0].len()) {
+                let mut pattern = 0;
+                for (r, dots) in DOTS.iter().enumerate() {
+                    for (c, dot) in dots.iter().enumerate() {
+                        let cell = self
+                            .data
+                            .get(block_row + r)
+                            .and_then(|row| row.get(block_column + c));
+                        if matches!(cell, Some(Cell::Empty | Cell::Number(_))) {
+                            pattern |= dot;
+                        }
+                    }
+                }
+                rendered.extend(char::from_u32(0x2800 + pattern));
+            }
+            rendered.push('\n');
+        }
+        rendered
+    }
+
+    /// Returns an owned copy of the board state, free of any presentation.
+    pub fn to_state(&self) -> BoardState {
+        BoardState {
+            rows: self.rows,
+            columns: self.columns,
+            mines: self.mines,
+            difficulty: self.difficulty(),
+            cells: self.data.clone(),
+        }
+    }
+
+    /// Serializes the board as JSON, see [`BoardState::to_json`]. Colors are
+    /// never included, so the output can be diffed or fed to other tools.
+    pub fn to_json(&self) -> String {
+        self.to_state().to_json()
+    }
+
+    /// Serializes the cells of the board as CSV, see [`BoardState::to_csv`].
+    pub fn to_csv(&self) -> String {
+        self.to_state().to_csv()
+    }
+
+    /// Returns a checksum of the board state, which changes whenever any of
+    /// its cells does, as well as the options or timer read along with them.
+    /// Useful to cheaply detect changes between two reads.
+    pub fn checksum(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (
+            self.rows,
+            self.columns,
+            self.mines,
+            self.options,
+            self.timer,
+            self.mine_counter,
+            self.status,
+        )
+            .hash(&mut hasher);
+        self.data.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Returns a fingerprint of the mine layout of the board, which identifies
+    /// a given game. Unlike [`Board::checksum`], it only depends on the board
+    /// dimensions and the positions of the mines, so it remains the same as
+    /// cells are revealed, and across runs of the tool.
+    pub fn layout_fingerprint(&self) -> u64 {
+        // FNV-1a, whose output is stable across builds and platforms
+        const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+        const FNV_PRIME: u64 = 0x100000001b3;
+        let mines = self.data.iter().enumerate().flat_map(|(r, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, cell)| **cell == Cell::Mine)
+                .map(move |(c, _)| (r, c))
+        });
+        [(self.rows, self.columns)]
+            .into_iter()
+            .chain(mines)
+            .flat_map(|(a, b)| [a as u64, b as u64])
+            .flat_map(u64::to_le_bytes)
+            .fold(FNV_OFFSET_BASIS, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+            })
+    }
+
+    /// Returns an owned copy of the cells of the board, as a list of rows.
+    ///
+    /// The result can be turned back into a board through [`Board::from_cells`].
+    pub fn to_2d_vec(&self) -> Vec<Vec<Cell>> {
+        self.data.clone()
+    }
+
+    /// Returns the connected regions of revealed cells without adjacent mines,
+    /// which are the areas the game opens at once on a single click. Each
+    /// region is given as a list of `(row, column)` coordinates.
+    pub fn empty_regions(&self) -> Vec<Vec<(usize, usize)>> {
+        let is_empty = |cell: &Cell| matches!(cell, Cell::Empty | Cell::Number(0));
+        let mut visited = vec![vec![false; self.columns]; self.rows];
+        let mut regions = Vec::new();
+        for r in 0..self.rows {
+            for c in 0..self.columns {
+                if visited[r][c] || !is_empty(&self.data[r][c]) {
+                    continue;
+                }
+                visited[r][c] = true;
+                let mut region = Vec::new();
+                let mut pending = vec![(r, c)];
+                while let Some((row, column)) = pending.pop() {
+                    region.push((row, column));
+                    for (nr, nc) in self.neighbors(row, column) {
+                        if !visited[nr][nc] && is_empty(&self.data[nr][nc]) {
+                            visited[nr][nc] = true;
+                            pending.push((nr, nc));
+                        }
+                    }
+                }
+                region.sort_unstable();
+                regions.push(region);
+            }
+        }
+        regions
+    }
+
+    /// Coordinates of the (up to eight) cells surrounding the given one.
+    pub(crate) fn neighbors(
+        &self,
+        row: usize,
+        column: usize,
+    ) -> impl Iterator<Item = (usize, usize)> {
+        let (rows, columns) = (self.rows, self.columns);
+        (row.saturating_sub(1)..=row + 1)
+            .flat_map(move |r| (column.saturating_sub(1)..=column + 1).map(move |c| (r, c)))
+            .filter(move |&(r, c)| r < rows && c < columns && (r, c) != (row, column))
+    }
+
+    pub(crate) fn insert(&mut self, value: Cell, row: usize, column: usize) -> Result<()> {
+        ensure!(row < self.rows, "Row {} does not exist", row);
+        ensure!(column < self.columns, "Column {} does not exist", column);
+        self.data[row][column] = value;
+        Ok(())
+    }
+}
+
+impl Display for Board {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> fmt::Result {
+        for r in 0..self.rows {
+            write!(f, "\t")?;
+            for c in 0..self.columns {
+                write!(f, "{} ", self.data[r][c])?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_regions_of_demo_board() {
+        let board = crate::demo_board().unwrap();
+        let regions = board.empty_regions();
+        let sizes: Vec<_> = regions.iter().map(Vec::len).collect();
+        assert_eq!(sizes, [43, 21, 2]);
+        // The column between the blocks joins the bottom left corner diagonally
+        assert!(regions[0].contains(&(0, 5)));
+        assert!(regions[0].contains(&(5, 0)));
+        assert!(regions[0].contains(&(8, 9)));
+        assert_eq!(regions[2], [(8, 14), (8, 15)]);
+    }
+
+    #[test]
+    fn empty_regions_only_join_empty_cells() {
+        let mut board = Board::from_glyphs(&[" 1. ", "11..", "....", " ..."], 3).unwrap();
+        // Numbers of zero mines are shown as empty cells
+        board.insert(Cell::Number(0), 3, 1).unwrap();
+        assert_eq!(
+            board.empty_regions(),
+            [vec![(0, 0)], vec![(0, 3)], vec![(3, 0), (3, 1)]]
+        );
+    }
+
+    #[test]
+    fn empty_regions_of_fresh_board() {
+        let board = Board::new(9, 9, 10);
+        assert!(board.empty_regions().is_empty());
+    }
+
+    #[test]
+    fn diff_of_same_board() {
+        let board = crate::demo_board().unwrap();
+        assert_eq!(board.diff(&board), BoardDiff::Cells(Vec::new()));
+    }
+
+    #[test]
+    fn diff_lists_changed_cells_in_order() {
+        let previous = Board::from_glyphs(&["...", "...", "..."], 1).unwrap();
+        let board = Board::from_glyphs(&[".1F", "...", "  ."], 1).unwrap();
+        assert_eq!(
+            board.diff(&previous),
+            BoardDiff::Cells(vec![(0, 1), (0, 2), (2, 0), (2, 1)])
+        );
+        // Cells going back to hidden are changes as well
+        assert_eq!(
+            previous.diff(&board),
+            BoardDiff::Cells(vec![(0, 1), (0, 2), (2, 0), (2, 1)])
+        );
+    }
+
+    #[test]
+    fn diff_of_boards_with_other_dimensions() {
+        let previous = Board::new(9, 9, 10);
+        assert_eq!(
+            Board::new(16, 16, 40).diff(&previous),
+            BoardDiff::DimensionsChanged
+        );
+        // Transposed boards have the same number of cells, but not dimensions
+        assert_eq!(
+            Board::new(16, 30, 99).diff(&Board::new(30, 16, 99)),
+            BoardDiff::DimensionsChanged
+        );
+    }
+
+    #[test]
+    fn render_changes_without_colors() {
+        colored::control::set_override(false);
+        let board = Board::from_glyphs(&["1*", "*."], 2).unwrap();
+        let rendered = board.render_with_changes(&[(0, 0), (0, 1)]);
+        assert_eq!(rendered, "\t1^*^\n\t* . \n");
+        // Marks never look like the glyph of a cell
+        let cells = [
+            Cell::Hidden,
+            Cell::Empty,
+            Cell::Flag,
+            Cell::Question,
+            Cell::Mine,
+            Cell::Unknown,
+        ];
+        let numbers = (0..=8).map(Cell::Number);
+        assert!(cells
+            .into_iter()
+            .chain(numbers)
+            .all(|cell| cell.glyph() != '^'));
+    }
+
+    #[test]
+    fn csv_lists_cell_tags_by_row() {
+        let board = Board::from_glyphs(&[".F3", "* ?"], 2).unwrap();
+        assert_eq!(board.to_csv(), "hidden,flag,3\nmine,empty,question\n");
+    }
+
+    #[test]
+    fn summary_counts_every_recognized_cell() {
+        let board = Board::from_glyphs(&["1*.", "F? ", "2!."], 3).unwrap();
+        let summary = board.summary();
+        assert_eq!((summary.revealed, summary.hidden), (3, 5));
+        assert_eq!(summary.flagged, 1);
+        // The unrecognized cell is the only one left out
+        assert_eq!(
+            summary.revealed + summary.hidden + 1,
+            summary.rows * summary.columns
+        );
+
+        // The demo board holds a single unrecognized cell as well
+        let summary = crate::demo_board().unwrap().summary();
+        assert_eq!(
+            summary.revealed + summary.hidden,
+            summary.rows * summary.columns - 1
+        );
+    }
+
+    #[test]
+    fn summary_of_won_board() {
+        // Every safe cell is opened, and mines left unflagged are hidden
+        let summary = Board::from_glyphs(&["1F", "1."], 2).unwrap().summary();
+        assert_eq!((summary.revealed, summary.hidden), (2, 2));
+        assert_eq!(
+            summary.revealed + summary.mines as usize,
+            summary.rows * summary.columns
+        );
+    }
+
+    #[test]
+    fn layout_fingerprint_ignores_revealed_cells() {
+        let board = Board::from_glyphs(&["*..", "...", "..*"], 2).unwrap();
+        let played = Board::from_glyphs(&["*1 ", "F? ", "1.*"], 2).unwrap();
+        assert_eq!(board.layout_fingerprint(), played.layout_fingerprint());
+        assert_ne!(board.checksum(), played.checksum());
+    }
+
+    #[test]
+    fn layout_fingerprint_depends_on_mines_and_dimensions() {
+        let board = Board::from_glyphs(&["*..", "...", "..*"], 2).unwrap();
+        let moved = Board::from_glyphs(&["*..", "..*", "..."], 2).unwrap();
+        assert_ne!(board.layout_fingerprint(), moved.layout_fingerprint());
+        let wider = Board::from_glyphs(&["*...", "....", "..*."], 2).unwrap();
+        assert_ne!(board.layout_fingerprint(), wider.layout_fingerprint());
+        let unplayed = Board::new(3, 3, 2);
+        assert_ne!(board.layout_fingerprint(), unplayed.layout_fingerprint());
+        assert_ne!(
+            Board::new(3, 4, 2).layout_fingerprint(),
+            Board::new(4, 3, 2).layout_fingerprint()
+        );
+    }
+
+    #[test]
+    fn layout_fingerprint_is_stable() {
+        // Fingerprints are meant to be compared across runs, so they must not
+        // depend on the hasher of the standard library
+        let board = Board::from_glyphs(&["*..", "...", "..*"], 2).unwrap();
+        assert_eq!(board.layout_fingerprint(), 0xab01d3a90e795145);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialized_state_matches_json() {
+        let state = Board::from_glyphs(&[".F3", "* ?"], 2).unwrap().to_state();
+        let serialized = serde_json::to_value(&state).unwrap();
+        let expected: serde_json::Value = serde_json::from_str(&state.to_json()).unwrap();
+        assert_eq!(serialized, expected);
+        assert_eq!(
+            serialized["cells"][0],
+            serde_json::json!(["hidden", "flag", "3"])
+        );
+    }
+}
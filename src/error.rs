@@ -0,0 +1,73 @@
+//! Well-known failure modes, which callers may want to tell apart from other
+//! errors.
+
+use crate::memory::PartialRead;
+use crate::Version;
+
+use std::fmt::{self, Display};
+
+/// Errors reported by the crate for well-known failure modes. They are
+/// returned wrapped in an [`anyhow::Error`], like every other error, and can be
+/// told apart through [`anyhow::Error::downcast_ref`], even when context was
+/// added to them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// No running game was found, of the given version if one was requested.
+    NoGameFound(Option<Version>),
+    /// Several games of the same version are running, and none was selected
+    /// by its PID.
+    SeveralGames {
+        /// Version of the games.
+        version: Version,
+        /// PIDs of the games, in ascending order.
+        pids: Vec<u32>,
+    },
+    /// A game was found, but reading its version is not supported.
+    UnsupportedVersion(Version),
+    /// The process with the given PID could not be opened, usually for lack
+    /// of privileges.
+    ProcessOpen(u32),
+    /// The image of the game targets a machine whose structures are not
+    /// supported, given as its `IMAGE_FILE_MACHINE` value.
+    UnsupportedArch(u16),
+    /// The named code pattern used to locate the game structures was not
+    /// found in the game image.
+    PatternNotFound(&'static str),
+    /// The memory of the game process could not be read.
+    MemoryRead(PartialRead),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoGameFound(None) => write!(f, "no Minesweeper game is running"),
+            Error::NoGameFound(Some(version)) => {
+                write!(f, "no {version} Minesweeper game is running")
+            }
+            Error::SeveralGames { version, pids } => {
+                let pids: Vec<_> = pids.iter().map(u32::to_string).collect();
+                write!(
+                    f,
+                    "several {} games are running (PIDs {}), select one by its PID",
+                    version,
+                    pids.join(", ")
+                )
+            }
+            Error::UnsupportedVersion(version) => write!(
+                f,
+                "detected the {version} version of Minesweeper, which is not supported yet"
+            ),
+            Error::ProcessOpen(pid) => write!(
+                f,
+                "failed to open process {pid}, running elevated may be required to access it"
+            ),
+            Error::UnsupportedArch(machine) => {
+                write!(f, "unsupported image machine {machine:#06x}")
+            }
+            Error::PatternNotFound(pattern) => write!(f, "{pattern} pattern not found in-memory"),
+            Error::MemoryRead(read) => write!(f, "{read}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -3,75 +3,61 @@
 
 #![warn(missing_docs)]
 
+mod board;
+mod error;
 pub mod memory;
 pub mod process;
+mod session;
+pub mod solver;
+mod theme;
 mod versions;
 
 pub use anyhow::Result;
+pub use board::{
+    Board, BoardDiff, BoardState, BoardSummary, Cell, Difficulty, GameOptions, GameStatus, Timer,
+};
+pub use colored::Color;
+pub use error::Error;
+pub use session::Session;
+pub use theme::{set_theme, CellStyle, Theme};
 
-use memory::MemoryHandle;
-use versions::{windows_7 as win7, windows_xp as winxp};
+use process::ProcessContext;
+use versions::{windows_10 as win10, windows_7 as win7, windows_xp as winxp};
 
-use anyhow::{bail, ensure, Context};
-use colored::*;
-use log::{debug, trace};
+use anyhow::{anyhow, Context};
+use log::{debug, trace, warn};
 use std::{
-    collections::HashMap,
     fmt::{self, Display},
+    io::{self, Write},
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    thread,
+    time::Duration,
 };
-use windows::Win32::{Foundation::*, System::Threading::*};
-
-/// Abstract representation of a Minesweeper game board, meant to be used
-/// for displaying the game state to the user.
-#[doc(hidden)]
-pub struct Board {
-    mines: u32,
-    rows: usize,
-    columns: usize,
-    data: Vec<Vec<ColoredString>>,
-}
-
-impl Board {
-    fn new(rows: usize, columns: usize, mines: u32) -> Board {
-        Board {
-            mines,
-            rows,
-            columns,
-            data: vec![vec![" ".into(); columns]; rows],
-        }
-    }
-
-    fn insert(&mut self, value: &ColoredString, row: usize, column: usize) -> Result<()> {
-        ensure!(row < self.rows, "Row {} does not exist", row);
-        ensure!(column < self.columns, "Column {} does not exist", column);
-        self.data[row][column] = value.clone();
-        Ok(())
-    }
-}
-
-impl Display for Board {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> fmt::Result {
-        for r in 0..self.rows {
-            write!(f, "\t")?;
-            for c in 0..self.columns {
-                write!(f, "{} ", self.data[r][c])?;
-            }
-            writeln!(f)?;
-        }
-        Ok(())
-    }
-}
 
-enum Version {
+/// Supported versions of the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    /// Minesweeper shipped with Windows XP (`WINMINE.EXE`).
     WindowsXP,
+    /// Minesweeper shipped with Windows 7 (`Minesweeper.exe`).
     Windows7,
+    /// Microsoft Minesweeper from the Microsoft Store, for Windows 8 and later
+    /// (`MicrosoftMinesweeper.exe`). It is detected, but its board cannot be
+    /// read yet.
+    Windows10,
+    /// Build handled by a reader added through [`register_version`], named
+    /// after its image name.
+    Custom(&'static str),
 }
 
 impl Display for Version {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let version = match self {
             Version::Windows7 => "Windows 7",
+            Version::Windows10 => "Windows 10",
             Version::WindowsXP => "Windows XP",
+            Version::Custom(image_name) => image_name,
         };
         write!(f, "{}", version)
     }
@@ -83,38 +69,351 @@ impl Display for Version {
 /// Running process will be searched for a known game implemenetation. If
 /// found, the game is accessed in-memory and the information relevant is retrieved
 /// and displayed on screen.
+///
+/// Detection only relies on identifiers that are not translated on localized
+/// Windows installations: executable image names first, then window class
+/// names. Window titles are never matched, as they differ between languages.
+///
+/// If several supported versions are running at the same time, the Windows 7
+/// game takes precedence over the Windows XP one. If several games of the same
+/// version are running, the PIDs of all of them are reported instead, so that
+/// one can be selected through [`info_by_pid`].
 pub fn info() -> Result<()> {
-    let version_map = HashMap::from([
-        ("Minesweeper.exe", Version::Windows7),
-        ("WINMINE.EXE", Version::WindowsXP),
-    ]);
-    debug!("Opening Minesweeper process");
-    let Some((pid, version)) = version_map.iter().find_map(|(name, version)| {
-        process::pid_by_name(name).map(|pid| (pid, version))
-    }) else {
-        bail!("no minesweeper in memory!");
-    };
-    debug!("Detected {} version running", version);
-    trace!("Minesweeper PID: {pid}");
-    let a_remote = unsafe {
-        let h_process: HANDLE = OpenProcess(
-            PROCESS_VM_READ | PROCESS_VM_OPERATION | PROCESS_QUERY_INFORMATION,
-            false,
-            pid,
-        )
-        .context("failed to open process")?;
-        trace!("Process handle: {:?}", h_process);
-        MemoryHandle::Process(h_process)
+    write_info(&mut io::stdout().lock(), Format::default())
+}
+
+/// Same as [`info`], but the board is printed as JSON, see [`Format::Json`].
+pub fn info_json() -> Result<()> {
+    write_info(&mut io::stdout().lock(), Format::Json)
+}
+
+/// Same as [`info`], but the game information is written to `out` in the
+/// given format, instead of the standard output.
+pub fn write_info(out: &mut impl Write, format: Format) -> Result<()> {
+    let board = read_board()?;
+    write_board(out, &board, None, format)
+}
+
+/// Same as [`write_info`], but waits for a game to be started instead of
+/// failing if none is running, searching for it every `poll` interval.
+pub fn write_info_when_found(out: &mut impl Write, format: Format, poll: Duration) -> Result<()> {
+    let context = ProcessContext::wait_for_game(poll)?;
+    let board = board(&context)?;
+    write_board(out, &board, None, format)
+}
+
+/// Same as [`info`], but the game is read from the process with the given PID
+/// instead of searching the running processes for one. Useful when several
+/// games run at once, or the executable of the game was renamed.
+pub fn info_by_pid(pid: u32) -> Result<()> {
+    write_pid_info(&mut io::stdout().lock(), Format::default(), pid)
+}
+
+/// Same as [`info`], but only games of the given version are searched for,
+/// skipping the detection of the version.
+pub fn info_for_version(version: Version) -> Result<()> {
+    write_version_info(&mut io::stdout().lock(), Format::default(), version)
+}
+
+/// Same as [`info_for_version`], but the game information is written to `out`
+/// in the given format, instead of the standard output.
+pub fn write_version_info(out: &mut impl Write, format: Format, version: Version) -> Result<()> {
+    let context = ProcessContext::detect_version(version)?;
+    let board = board(&context)?;
+    write_board(out, &board, None, format)
+}
+
+/// Same as [`info_by_pid`], but the game information is written to `out` in
+/// the given format, instead of the standard output.
+pub fn write_pid_info(out: &mut impl Write, format: Format, pid: u32) -> Result<()> {
+    let board = read_board_by_pid(pid)?;
+    write_board(out, &board, None, format)
+}
+
+/// Same as [`write_info`], but the game is read from a minidump of its
+/// process, such as the ones written by the Task Manager, instead of a
+/// running process.
+pub fn write_dump_info(out: &mut impl Write, format: Format, path: &Path) -> Result<()> {
+    let context = ProcessContext::from_dump(path)?;
+    let board = board(&context)?;
+    write_board(out, &board, None, format)
+}
+
+/// Formats in which boards can be written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Board dimensions and mine count, followed by the grid of cells.
+    #[default]
+    Grid,
+    /// Same as [`Format::Grid`], followed by a legend of the glyphs, see
+    /// [`Board::render_with_legend`].
+    Legend,
+    /// Same as [`Format::Grid`], highlighting the cells deduced by
+    /// [`solver::solve`], see [`Board::render_with_deductions`].
+    Hints,
+    /// Same as [`Format::Grid`], underlining the cells changed since the
+    /// previous read when watching the game, see [`Board::render_with_changes`].
+    Changes,
+    /// Compact mini-map of the opened cells, see [`Board::render_braille`].
+    Braille,
+    /// Machine-readable board state, see [`Board::to_json`].
+    Json,
+    /// Cells of the board as CSV, see [`Board::to_csv`].
+    Csv,
+}
+
+/// Whether boards are rendered with ANSI colors, see [`set_color_mode`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Colors are used unless the `NO_COLOR` environment variable is set or
+    /// the standard output is not a terminal, such as when piped to a file.
+    #[default]
+    Auto,
+    /// Colors are always used.
+    Always,
+    /// Colors are never used, rendering boards with plain ASCII glyphs only.
+    Never,
+}
+
+/// Selects whether boards written from now on are colored. Without calling
+/// it, [`ColorMode::Auto`] is used.
+pub fn set_color_mode(mode: ColorMode) {
+    match mode {
+        ColorMode::Auto => colored::control::unset_override(),
+        ColorMode::Always => colored::control::set_override(true),
+        ColorMode::Never => colored::control::set_override(false),
+    }
+}
+
+/// Polling interval used by [`watch`].
+#[derive(Debug, Clone, Copy)]
+pub enum WatchInterval {
+    /// The board is read again after a fixed delay.
+    Fixed(Duration),
+    /// The delay is reset to `min` whenever the board changes, and doubled
+    /// after every read without changes, up to `max`. This keeps updates
+    /// responsive during play while idling cheaply otherwise.
+    Adaptive {
+        /// Delay right after a change of the board.
+        min: Duration,
+        /// Upper bound of the delay while the board remains unchanged.
+        max: Duration,
+    },
+}
+
+/// Command for continuously displaying the state of an active Minesweeper
+/// game. The screen is redrawn every time the board changes, polling the game
+/// at the given interval.
+///
+/// The game process is opened and its structures located once, see
+/// [`Session`]. Reads failing while the process runs, such as while a new game
+/// resizes the board, are retried on the next poll. Once it exits, watching
+/// stops without error.
+pub fn watch(interval: WatchInterval, format: Format) -> Result<()> {
+    let mut session = Session::open()?;
+    let mut last_checksum = None;
+    let mut last_board = None;
+    let mut delay = match interval {
+        WatchInterval::Fixed(delay) => delay,
+        WatchInterval::Adaptive { min, .. } => min,
     };
-    let board = match version {
-        Version::WindowsXP => winxp::board(a_remote),
-        Version::Windows7 => win7::board(a_remote),
+    let mut failing = false;
+    while session.is_running() {
+        let board = match session.refresh() {
+            Ok(board) => board,
+            // Reads fail once the process exits, which ends the loop
+            Err(_) if !session.is_running() => break,
+            Err(error) => {
+                // Only the first failure in a row is reported, since the game
+                // may take several polls to settle
+                match failing {
+                    false => warn!("Unable to read the board, retrying: {:#}", error),
+                    true => debug!("Unable to read the board, retrying: {:#}", error),
+                }
+                failing = true;
+                thread::sleep(delay);
+                continue;
+            }
+        };
+        failing = false;
+        let checksum = board.checksum();
+        let changed = last_checksum != Some(checksum);
+        if changed {
+            let mut out = io::stdout().lock();
+            // Clear the screen and move the cursor to the top left corner, so
+            // that no leftovers remain when a new game changes the dimensions
+            write!(out, "\x1b[2J\x1b[H")?;
+            write_board(&mut out, &board, last_board.as_ref(), format)?;
+            out.flush()?;
+            last_checksum = Some(checksum);
+            last_board = Some(board);
+        }
+        if let WatchInterval::Adaptive { min, max } = interval {
+            delay = if changed { min } else { (delay * 2).min(max) };
+            trace!("Next board read in {:?}", delay);
+        }
+        thread::sleep(delay);
+    }
+    println!("Game process exited");
+    Ok(())
+}
+
+/// Detects a running game and reads its board, the same way as [`info`] does,
+/// but handing the board back instead of displaying it. Useful to inspect the
+/// game state programmatically.
+pub fn read_board() -> Result<Board> {
+    let context = ProcessContext::detect()?;
+    board(&context)
+}
+
+/// Same as [`read_board`], but the game is read from the process with the
+/// given PID, see [`info_by_pid`].
+pub fn read_board_by_pid(pid: u32) -> Result<Board> {
+    let context = ProcessContext::open_pid(pid)?;
+    board(&context)
+}
+
+/// Shows every mine of the running Windows XP game in its window, as the game
+/// does once lost, returning the number of mines revealed. Unlike the rest of
+/// the crate, this writes to the memory of the game, so it is never done
+/// implicitly. Other versions are not supported.
+pub fn reveal_mines() -> Result<usize> {
+    let version = Version::WindowsXP;
+    let context = ProcessContext::open_writable(process::game_pid(version)?, version)?;
+    winxp::reveal_mines(&context)
+}
+
+fn board(context: &ProcessContext) -> Result<Board> {
+    match context.version {
+        Version::WindowsXP => winxp::board(context),
+        Version::Windows7 => win7::board(context),
+        Version::Windows10 => win10::board(context),
+        Version::Custom(image_name) => {
+            let reader = lock_custom_versions()
+                .iter()
+                .find(|(name, _)| *name == image_name)
+                .map(|(_, reader)| Arc::clone(reader))
+                .ok_or(anyhow!("no reader registered for {image_name}"))?;
+            reader(context)
+        }
+    }
+    .context("unable to retrieve game board")
+}
+
+/// Reader of the board of a custom version of the game.
+pub type VersionReader = dyn Fn(&ProcessContext) -> Result<Board> + Send + Sync;
+
+/// Readers added through [`register_version`], in order of registration.
+static CUSTOM_VERSIONS: Mutex<Vec<(&'static str, Arc<VersionReader>)>> = Mutex::new(Vec::new());
+
+fn lock_custom_versions() -> MutexGuard<'static, Vec<(&'static str, Arc<VersionReader>)>> {
+    CUSTOM_VERSIONS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Registers a reader for processes running the `image_name` executable, so
+/// that builds unknown to this crate can be supported without modifying it.
+///
+/// Registered versions are detected by [`info`], [`watch`] and
+/// [`process::find_game`] after the built-in ones, which keep their
+/// precedence. Registering an image name again replaces its reader.
+pub fn register_version(image_name: &str, reader: Box<VersionReader>) {
+    let mut versions = lock_custom_versions();
+    match versions.iter_mut().find(|(name, _)| *name == image_name) {
+        Some((_, registered)) => *registered = Arc::from(reader),
+        // Names are leaked so that versions can be referenced by the
+        // `Copy` [`Version`] enum; registrations are expected to be few
+        None => versions.push((Box::leak(image_name.into()), Arc::from(reader))),
+    }
+}
+
+/// Returns the image names registered through [`register_version`].
+pub(crate) fn custom_versions() -> Vec<(&'static str, Version)> {
+    lock_custom_versions()
+        .iter()
+        .map(|&(name, _)| (name, Version::Custom(name)))
+        .collect()
+}
+
+/// Writes a synthetic board to `out`, covering every [`Cell`] variant. No
+/// game is accessed, which makes it useful to preview how boards are rendered.
+pub fn write_demo(out: &mut impl Write, format: Format) -> Result<()> {
+    write_board(out, &demo_board()?, None, format)
+}
+
+/// Synthetic board written by [`write_demo`], also used as a fixture by tests.
+pub(crate) fn demo_board() -> Result<Board> {
+    const DEMO_MINES: u32 = 26;
+    const DEMO_BOARD: [&str; 9] = [
+        "12321 12321     ",
+        "2***2 2FFF2     ",
+        "3*8*3 3F7F31    ",
+        "2***2 2FF5F2    ",
+        "12321 123F!4321 ",
+        "        124?..2 ",
+        "          3.6.2 ",
+        "          2.4...",
+        "          12.1  ",
+    ];
+    Board::from_glyphs(&DEMO_BOARD, DEMO_MINES)
+}
+
+/// Writes the board in the given format. The `previous` board read, if any, is
+/// only used by [`Format::Changes`].
+fn write_board(
+    out: &mut impl Write,
+    board: &Board,
+    previous: Option<&Board>,
+    format: Format,
+) -> Result<()> {
+    match format {
+        Format::Grid | Format::Legend | Format::Hints | Format::Changes => {
+            write!(
+                out,
+                "Field: {} r x {} c, Mines: {} ({})",
+                board.rows,
+                board.columns,
+                board.mines,
+                board.difficulty()
+            )?;
+            match board.mine_counter {
+                Some(counter) => writeln!(out, ", Left: {counter}")?,
+                None => writeln!(out)?,
+            }
+            if let Some(options) = board.options {
+                writeln!(out, "{options}")?;
+            }
+            if let Some(timer) = board.timer {
+                writeln!(out, "{timer}")?;
+            }
+            if let Some(status) = board.status {
+                writeln!(out, "{status}")?;
+            }
+            match format {
+                Format::Legend => writeln!(out, "\n{}", board.render_with_legend())?,
+                Format::Hints => {
+                    let deductions = board.solve();
+                    writeln!(out, "\n{}", board.render_with_deductions(&deductions))?;
+                    writeln!(
+                        out,
+                        "Safe: {}, Mines: {}",
+                        deductions.safe.len(),
+                        deductions.mines.len()
+                    )?;
+                }
+                Format::Changes => match previous.map(|previous| board.diff(previous)) {
+                    Some(BoardDiff::Cells(changed)) => {
+                        writeln!(out, "\n{}", board.render_with_changes(&changed))?
+                    }
+                    _ => writeln!(out, "\n{board}")?,
+                },
+                _ => writeln!(out, "\n{board}")?,
+            }
+        }
+        Format::Braille => write!(out, "{}", board.render_braille())?,
+        Format::Json => writeln!(out, "{}", board.to_json())?,
+        Format::Csv => write!(out, "{}", board.to_csv())?,
     }
-    .context("unable to retrieve game board")?;
-    println!(
-        "Field: {} r x {} c, Mines: {}",
-        board.rows, board.columns, board.mines
-    );
-    println!("\n{board}");
     Ok(())
 }
@@ -1,7 +1,271 @@
-use anyhow::Result;
+use anyhow::{ensure, Context, Result};
+use clap::{Parser, ValueEnum};
+use mimisweep::{ColorMode, Format, Theme, Version, WatchInterval};
+use std::{
+    fs::File,
+    io::{self, Write},
+    panic,
+    path::PathBuf,
+    process::ExitCode,
+    time::Duration,
+};
 
-fn main() -> Result<()> {
+/// Interval between searches for a game process with `--poll-until-found`.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Exit code used when no game is running, so that scripts can tell it apart
+/// from other failures, which exit with 1.
+const EXIT_NO_GAME: u8 = 2;
+
+/// Reads the board of a running Minesweeper game from its memory, supporting
+/// the Windows XP and Windows 7 versions of the game.
+#[derive(Parser)]
+#[command(version, about)]
+struct Args {
+    /// Write the board to FILE as plain text, without colors
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+    /// Render a synthetic board covering every cell kind, without reading any game
+    #[arg(long, group = "source")]
+    demo: bool,
+    /// Read the game from a minidump of its process instead of a running one
+    #[arg(long, value_name = "FILE", group = "source")]
+    dump: Option<PathBuf>,
+    /// Read the game from the process with the given ID
+    #[arg(long, group = "source")]
+    pid: Option<u32>,
+    /// Only search for games of the given version, skipping its detection
+    #[arg(long, value_enum, value_name = "VERSION", group = "source")]
+    version_hint: Option<VersionHint>,
+    /// Render a compact mini-map of the opened cells using braille characters
+    #[arg(long, group = "format")]
+    braille: bool,
+    /// Append a legend explaining the glyphs of the board
+    #[arg(long, group = "format")]
+    legend: bool,
+    /// Highlight the cells deduced to be safe (green) or to hold a mine (red)
+    #[arg(long, group = "format")]
+    hints: bool,
+    /// Underline the cells changed since the board was last drawn, with --watch
+    #[arg(long, group = "format")]
+    changes: bool,
+    /// Print the board as a JSON object
+    #[arg(long, group = "format")]
+    json: bool,
+    /// Print the cells of the board as CSV, one line per row
+    #[arg(long, group = "format")]
+    csv: bool,
+    /// Always use colors
+    #[arg(long, overrides_with = "no_color")]
+    color: bool,
+    /// Never use colors
+    #[arg(long, overrides_with = "color")]
+    no_color: bool,
+    /// Colors of the cells
+    #[arg(long, value_enum, value_name = "THEME")]
+    theme: Option<ThemeName>,
+    /// Wait for a game to be started, then read it once
+    #[arg(long, group = "source")]
+    poll_until_found: bool,
+    /// Read the game every SECS seconds, redrawing the board when it changes
+    #[arg(
+        long,
+        value_name = "SECS",
+        value_parser = parse_interval,
+        group = "watch_mode",
+        conflicts_with_all = ["output", "source"],
+    )]
+    watch: Option<Duration>,
+    /// Watch the game, polling every MIN seconds after a change and backing
+    /// off up to every MAX seconds while the board stays the same
+    #[arg(
+        long,
+        value_name = "MIN:MAX",
+        value_parser = parse_bounds,
+        group = "watch_mode",
+        conflicts_with_all = ["output", "source"],
+    )]
+    interval_adaptive: Option<(Duration, Duration)>,
+}
+
+/// Values of `--version-hint`.
+#[derive(Clone, Copy, ValueEnum)]
+enum VersionHint {
+    /// Windows XP (WINMINE.EXE)
+    Xp,
+    /// Windows 7 (Minesweeper.exe)
+    Win7,
+}
+
+/// Values of `--theme`.
+#[derive(Clone, Copy, ValueEnum)]
+enum ThemeName {
+    /// Colors of the game itself
+    Classic,
+    /// No colors, only using bold for flags, mines and unrecognized cells
+    Monochrome,
+}
+
+impl Args {
+    fn format(&self) -> Format {
+        match self {
+            _ if self.braille => Format::Braille,
+            _ if self.legend => Format::Legend,
+            _ if self.hints => Format::Hints,
+            _ if self.changes => Format::Changes,
+            _ if self.json => Format::Json,
+            _ if self.csv => Format::Csv,
+            _ => Format::default(),
+        }
+    }
+
+    fn watch_interval(&self) -> Option<WatchInterval> {
+        match (self.watch, self.interval_adaptive) {
+            (Some(interval), _) => Some(WatchInterval::Fixed(interval)),
+            (_, Some((min, max))) => Some(WatchInterval::Adaptive { min, max }),
+            _ => None,
+        }
+    }
+}
+
+fn main() -> ExitCode {
     pretty_env_logger::init();
-    mimisweep::info()?;
+    install_panic_hook();
+    let args = match Args::try_parse() {
+        Ok(args) => args,
+        Err(error) => {
+            // Usage errors exit with 1 rather than clap's 2, which is kept
+            // for when no game is running
+            let _ = error.print();
+            return match error.use_stderr() {
+                true => ExitCode::FAILURE,
+                false => ExitCode::SUCCESS,
+            };
+        }
+    };
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {error:?}");
+            match error.downcast_ref::<mimisweep::Error>() {
+                Some(mimisweep::Error::NoGameFound(_)) => ExitCode::from(EXIT_NO_GAME),
+                _ => ExitCode::FAILURE,
+            }
+        }
+    }
+}
+
+fn run(args: Args) -> Result<()> {
+    let format = args.format();
+    if args.color {
+        mimisweep::set_color_mode(ColorMode::Always);
+    } else if args.no_color {
+        mimisweep::set_color_mode(ColorMode::Never);
+    }
+    if let Some(theme) = args.theme {
+        mimisweep::set_theme(match theme {
+            ThemeName::Classic => Theme::CLASSIC,
+            ThemeName::Monochrome => Theme::MONOCHROME,
+        });
+    }
+    if let Some(interval) = args.watch_interval() {
+        return mimisweep::watch(interval, format);
+    }
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => {
+            // Files only hold plain text, never ANSI escape sequences, which
+            // overrides any --color given
+            mimisweep::set_color_mode(ColorMode::Never);
+            Box::new(
+                File::create(path)
+                    .with_context(|| format!("failed to create {}", path.display()))?,
+            )
+        }
+        None => Box::new(io::stdout().lock()),
+    };
+    if args.demo {
+        mimisweep::write_demo(&mut out, format)?;
+    } else if let Some(path) = &args.dump {
+        mimisweep::write_dump_info(&mut out, format, path)?;
+    } else if let Some(pid) = args.pid {
+        mimisweep::write_pid_info(&mut out, format, pid)?;
+    } else if let Some(hint) = args.version_hint {
+        let version = match hint {
+            VersionHint::Xp => Version::WindowsXP,
+            VersionHint::Win7 => Version::Windows7,
+        };
+        mimisweep::write_version_info(&mut out, format, version)?;
+    } else if args.poll_until_found {
+        mimisweep::write_info_when_found(&mut out, format, POLL_INTERVAL)?;
+    } else {
+        mimisweep::write_info(&mut out, format)?;
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Extends the default panic hook to print diagnostics about the game process
+/// being read, so that crashes can be reported with some context.
+fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        if let Some(target) = mimisweep::process::last_target() {
+            eprintln!("\nTarget process diagnostics:\n{target}");
+        }
+    }));
+}
+
+/// Parses a number of seconds, possibly fractional.
+fn parse_duration(secs: &str) -> Result<Duration> {
+    let secs: f64 = secs
+        .parse()
+        .with_context(|| format!("invalid number of seconds: {secs}"))?;
+    Duration::try_from_secs_f64(secs).with_context(|| format!("invalid interval: {secs}"))
+}
+
+/// Parses the interval of `--watch`, which must be positive so that the game
+/// is not polled in a busy loop.
+fn parse_interval(secs: &str) -> Result<Duration> {
+    let interval = parse_duration(secs)?;
+    ensure!(!interval.is_zero(), "watch interval must be positive");
+    Ok(interval)
+}
+
+/// Parses the `MIN:MAX` bounds of `--interval-adaptive`.
+fn parse_bounds(bounds: &str) -> Result<(Duration, Duration)> {
+    let (min, max) = bounds
+        .split_once(':')
+        .context("bounds must be given as MIN:MAX")?;
+    let (min, max) = (parse_duration(min)?, parse_duration(max)?);
+    ensure!(!min.is_zero(), "minimum interval must be positive");
+    ensure!(min <= max, "minimum interval exceeds the maximum");
+    Ok((min, max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, clap::Error> {
+        Args::try_parse_from([&["mimisweep"], args].concat())
+    }
+
+    #[test]
+    fn sources_cannot_be_combined() {
+        assert!(parse(&["--demo", "--pid", "1"]).is_err());
+        assert!(parse(&["--poll-until-found", "--pid", "1"]).is_err());
+        assert!(parse(&["--poll-until-found", "--version-hint", "xp"]).is_err());
+        assert!(parse(&["--demo", "--json"]).is_ok());
+    }
+
+    #[test]
+    fn watching_only_reads_running_games() {
+        for watch in [["--watch", "1"], ["--interval-adaptive", "1:2"]] {
+            for source in [&["--demo"][..], &["--poll-until-found"], &["--pid", "1"]] {
+                assert!(parse(&[&watch[..], source].concat()).is_err());
+            }
+            assert!(parse(&[&watch[..], &["--output", "board.txt"]].concat()).is_err());
+            assert!(parse(&[&watch[..], &["--hints"]].concat()).is_ok());
+        }
+    }
+}
@@ -1,9 +1,26 @@
 //! Memory-releated tools used to interface with Windows processes.
 
-use anyhow::{anyhow, Context, Result};
-use memchr::memmem;
-use std::{ffi::c_void, mem, ops::Deref, ptr::addr_of_mut};
-use windows::Win32::{Foundation::*, System::Diagnostics::Debug::*};
+use crate::process::SectionInfo;
+use crate::Error;
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use log::{debug, trace, warn};
+use memchr::{memchr_iter, memmem};
+use std::{
+    ffi::c_void,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    mem,
+    path::Path,
+    ptr::{self, addr_of_mut},
+    slice,
+};
+use windows::core::HSTRING;
+use windows::Win32::{
+    Foundation::*,
+    Storage::FileSystem::*,
+    System::{Diagnostics::Debug::*, Memory::*, SystemServices::*, Threading::GetProcessId},
+};
 
 /// Memory handle abstraction for dealing with different types of memory access.
 /// Implements the RAII pattern for automatic deallocation of any associated handles.
@@ -15,35 +32,223 @@ pub enum MemoryHandle {
     /// Running process memory
     Process(HANDLE),
     /// File access
-    File(HANDLE),
+    File(FileMemory),
     /// Kernel access
     Kernel(HANDLE),
     /// Memory dump
-    Dump,
+    Dump(Minidump),
+}
+
+impl MemoryHandle {
+    /// Takes ownership of a process handle, making sure it can be used to
+    /// query the process before any memory is read through it.
+    ///
+    /// Null and `INVALID_HANDLE_VALUE` handles are rejected upfront, so that a
+    /// failed `OpenProcess` is reported here rather than by the first read.
+    pub fn from_process_handle(handle: HANDLE) -> Result<Self> {
+        // Covers both null and INVALID_HANDLE_VALUE
+        ensure!(!handle.is_invalid(), "invalid process handle");
+        let memory = Self::Process(handle);
+        // Probe that the handle refers to a process it is allowed to query
+        let pid = unsafe { GetProcessId(handle) };
+        ensure!(pid != 0, "handle does not grant access to a process");
+        Ok(memory)
+    }
+
+    /// Opens a file for reading, such as a raw memory dump or an image on disk.
+    /// Pointers used to read from the returned handle are file offsets.
+    pub fn open_file(path: &Path) -> Result<Self> {
+        Ok(Self::File(FileMemory {
+            handle: create_file(path)?,
+            image_base: None,
+            ranges: None,
+        }))
+    }
+
+    /// Opens a PE image on disk, such as the game executable, for static
+    /// analysis. Pointers used to read from the returned handle are virtual
+    /// addresses of the image loaded at its preferred base, see
+    /// [`FileMemory::image_base`], which are translated to file offsets through
+    /// its section table. This way, offsets found in the image match the ones
+    /// found in the memory of a running process.
+    pub fn open_image(path: &Path) -> Result<Self> {
+        let mut memory = Self::open_file(path)?;
+        if let Self::File(file) = &mut memory {
+            let (image_base, ranges) =
+                unsafe { image_ranges(file.handle) }.context("invalid PE image")?;
+            debug!(
+                "Image based at {:#x} with {} mapped ranges",
+                image_base,
+                ranges.len()
+            );
+            file.image_base = Some(image_base);
+            file.ranges = Some(ranges);
+        }
+        Ok(memory)
+    }
+
+    /// Opens a minidump file of a process, see [`Minidump`].
+    pub fn from_dump(path: &Path) -> Result<Self> {
+        Ok(Self::Dump(Minidump::open(path)?))
+    }
+
+    /// Returns the underlying handle, if the memory is accessed through one.
+    /// The memory of the current process is accessed through its pseudo
+    /// handle, as returned by `GetCurrentProcess`.
+    pub fn as_handle(&self) -> Result<HANDLE> {
+        match self {
+            Self::Own => Ok(CURRENT_PROCESS),
+            Self::Process(handle) | Self::Kernel(handle) => Ok(*handle),
+            Self::File(file) => Ok(file.handle),
+            Self::Dump(_) => bail!("{} memory is not accessed through a handle", self.kind()),
+        }
+    }
+
+    /// Returns a short name describing the kind of memory being accessed,
+    /// suitable for logs and error messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Own => "own process",
+            Self::Process(_) => "process",
+            Self::File(_) => "file",
+            Self::Kernel(_) => "kernel",
+            Self::Dump(_) => "dump",
+        }
+    }
 }
 
 impl Drop for MemoryHandle {
     fn drop(&mut self) {
-        match self {
-            Self::Process(handle) => unsafe {
-                CloseHandle(*handle);
-            },
-            _ => unimplemented!("Drop trait not implemented for {:?}", &self),
+        let handle = match self {
+            Self::Process(handle) | Self::Kernel(handle) => *handle,
+            Self::File(file) => file.handle,
+            // The dump file is closed when dropped
+            Self::Own | Self::Dump(_) => return,
+        };
+        // Sentinels are never closed, as they may stand for another handle,
+        // such as INVALID_HANDLE_VALUE for the current process
+        if handle.is_invalid() {
+            return;
+        }
+        if !unsafe { CloseHandle(handle) }.as_bool() {
+            warn!("Failed to close {} handle {:?}", self.kind(), handle);
         }
     }
 }
 
-impl Deref for MemoryHandle {
-    type Target = HANDLE;
+/// Pseudo handle standing for the current process, as returned by
+/// `GetCurrentProcess`. It needs no closing, see [`MemoryHandle::as_handle`].
+const CURRENT_PROCESS: HANDLE = HANDLE(-1);
 
-    fn deref(&self) -> &Self::Target {
-        match self {
-            MemoryHandle::Process(handle) => handle,
-            _ => unimplemented!("Deref trait not implemented for {:?}", &self),
+/// Opens an existing file for reading.
+fn create_file(path: &Path) -> Result<HANDLE> {
+    let name = path.to_str().context("file path is not valid unicode")?;
+    unsafe {
+        CreateFileW(
+            &HSTRING::from(name),
+            FILE_GENERIC_READ.0,
+            FILE_SHARE_READ,
+            None,
+            OPEN_EXISTING,
+            FILE_ATTRIBUTE_NORMAL,
+            None,
+        )
+    }
+    .with_context(|| format!("failed to open {}", path.display()))
+}
+
+/// File opened through [`MemoryHandle::open_file`] or
+/// [`MemoryHandle::open_image`].
+#[derive(Debug)]
+pub struct FileMemory {
+    handle: HANDLE,
+    image_base: Option<u64>,
+    /// Virtual ranges of the image backed by the file, if opened as an image.
+    ranges: Option<Vec<MappedRange>>,
+}
+
+impl FileMemory {
+    /// Returns the preferred base address of the image, if the file was
+    /// opened as an image.
+    pub fn image_base(&self) -> Option<*const c_void> {
+        self.image_base.map(|base| base as *const c_void)
+    }
+
+    /// Translates a pointer into the offset of the file holding `size` bytes
+    /// from it.
+    fn offset(&self, address: u64, size: usize) -> Result<u64> {
+        match &self.ranges {
+            Some(ranges) => mapped_offset(ranges, address, size),
+            None => Ok(address),
         }
     }
 }
 
+/// Returns the virtual ranges of a PE image backed by the file, along with
+/// the preferred base address of the image. The headers and the raw data of
+/// every section are mapped, while uninitialized data is left out.
+unsafe fn image_ranges(file: HANDLE) -> Result<(u64, Vec<MappedRange>)> {
+    let dos_header: IMAGE_DOS_HEADER = read_at(file, 0)?;
+    ensure!(
+        dos_header.e_magic == IMAGE_DOS_SIGNATURE,
+        "invalid DOS signature"
+    );
+    let p_nt_headers = dos_header.e_lfanew as u64;
+    let signature: u32 = read_at(file, p_nt_headers)?;
+    ensure!(signature == IMAGE_NT_SIGNATURE, "invalid NT signature");
+    let file_header: IMAGE_FILE_HEADER = read_at(file, p_nt_headers + 4)?;
+    let p_optional_header = p_nt_headers + 4 + mem::size_of::<IMAGE_FILE_HEADER>() as u64;
+    let (image_base, size_of_headers) = match read_at(file, p_optional_header)? {
+        IMAGE_NT_OPTIONAL_HDR32_MAGIC => {
+            let header: IMAGE_OPTIONAL_HEADER32 = read_at(file, p_optional_header)?;
+            (header.ImageBase as u64, header.SizeOfHeaders)
+        }
+        IMAGE_NT_OPTIONAL_HDR64_MAGIC => {
+            let header: IMAGE_OPTIONAL_HEADER64 = read_at(file, p_optional_header)?;
+            (header.ImageBase, header.SizeOfHeaders)
+        }
+        magic => bail!("unknown optional header magic {:#x}", magic.0),
+    };
+    let mut ranges =
+        vec![MappedRange::new(image_base, size_of_headers as u64, 0)
+            .context("invalid image headers")?];
+    let p_sections = p_optional_header + file_header.SizeOfOptionalHeader as u64;
+    for i in 0..file_header.NumberOfSections as u64 {
+        let header: IMAGE_SECTION_HEADER = read_at(
+            file,
+            p_sections + i * mem::size_of::<IMAGE_SECTION_HEADER>() as u64,
+        )?;
+        let section = SectionInfo::from(&header);
+        let size = match section.virtual_size {
+            0 => section.raw_size,
+            virtual_size => virtual_size.min(section.raw_size),
+        };
+        if size > 0 {
+            let range = image_base
+                .checked_add(section.virtual_address as u64)
+                .and_then(|start| {
+                    MappedRange::new(start, size as u64, section.raw_address as u64).ok()
+                })
+                .with_context(|| format!("invalid range of section {}", i))?;
+            ranges.push(range);
+        }
+    }
+    ranges.sort_by_key(|range| range.start);
+    Ok((image_base, ranges))
+}
+
+/// Reads an object from a file, at the given offset.
+unsafe fn read_at<T>(file: HANDLE, offset: u64) -> Result<T> {
+    let mut data: T = mem::zeroed();
+    read_file(
+        file,
+        offset,
+        addr_of_mut!(data) as *mut _,
+        mem::size_of::<T>(),
+    )?;
+    Ok(data)
+}
+
 /// Returns a copy of an object, read from the resource pointed by the given
 /// [`MemoryHandle`].
 ///
@@ -53,26 +258,45 @@ impl Deref for MemoryHandle {
 /// specified type. The caller is responsbile for checking if the returned object
 /// is indeed a valid instance of the requested type.
 pub unsafe fn copy<T>(memory: &MemoryHandle, data_ptr: *const T) -> Result<T> {
+    ensure!(!data_ptr.is_null(), "attempted to read from a null pointer");
     match memory {
+        MemoryHandle::Own => read_from_own(data_ptr),
         MemoryHandle::Process(handle) => read_from_process(*handle, data_ptr),
-        _ => unimplemented!("copy not implemented for {:?}", memory),
+        MemoryHandle::File(file) => read_from_file(file, data_ptr),
+        MemoryHandle::Dump(dump) => {
+            let mut data: T = mem::zeroed();
+            dump.read(
+                data_ptr as u64,
+                addr_of_mut!(data) as *mut u8,
+                mem::size_of::<T>(),
+            )?;
+            Ok(data)
+        }
+        MemoryHandle::Kernel(_) => bail!("reading is not supported for {} memory", memory.kind()),
     }
 }
 
+/// Reads an object from the memory of the current process. No alignment is
+/// required, so that objects can be read from any offset of a buffer.
+unsafe fn read_from_own<T>(data_ptr: *const T) -> Result<T> {
+    Ok(ptr::read_unaligned(data_ptr))
+}
+
 unsafe fn read_from_process<T>(process: HANDLE, data_ptr: *const T) -> Result<T> {
     let mut data: T = mem::zeroed();
-    unsafe {
-        ReadProcessMemory(
-            process,
-            data_ptr as *mut _,
-            addr_of_mut!(data) as *mut _,
-            mem::size_of::<T>(),
-            None,
-        )
-    }
-    .as_bool()
-    .then_some(data)
-    .ok_or(anyhow!("error reading memory of remote process"))
+    read_process(
+        process,
+        data_ptr as *const _,
+        addr_of_mut!(data) as *mut _,
+        mem::size_of::<T>(),
+    )?;
+    Ok(data)
+}
+
+/// Reads an object from a file, at the offset `data_ptr` translates to.
+unsafe fn read_from_file<T>(file: &FileMemory, data_ptr: *const T) -> Result<T> {
+    let offset = file.offset(data_ptr as u64, mem::size_of::<T>())?;
+    read_at(file.handle, offset)
 }
 
 /// Returns a vector of elements, read from the resource pointed by the given
@@ -91,12 +315,37 @@ pub unsafe fn copy_array<T>(
 where
     T: Clone + Default,
 {
+    ensure!(!data_ptr.is_null(), "attempted to read from a null pointer");
     match memory {
+        MemoryHandle::Own => read_array_from_own(data_ptr, count),
         MemoryHandle::Process(handle) => read_array_from_process(*handle, data_ptr, count),
-        _ => unimplemented!("copy_array not implemented for {:?}", memory),
+        MemoryHandle::File(file) => read_array_from_file(file, data_ptr, count),
+        MemoryHandle::Dump(dump) => {
+            let mut vec = vec![Default::default(); count];
+            let size = mem::size_of::<T>()
+                .checked_mul(count)
+                .ok_or(anyhow!("invalid read, overflow in array size"))?;
+            dump.read(data_ptr as u64, vec.as_mut_ptr() as *mut u8, size)?;
+            Ok(vec)
+        }
+        MemoryHandle::Kernel(_) => bail!("reading is not supported for {} memory", memory.kind()),
     }
 }
 
+/// Same as [`read_from_own`], for arrays of elements.
+unsafe fn read_array_from_own<T>(data_ptr: *const T, count: usize) -> Result<Vec<T>>
+where
+    T: Clone + Default,
+{
+    let mut vec = vec![Default::default(); count];
+    let size = mem::size_of::<T>()
+        .checked_mul(count)
+        .ok_or(anyhow!("invalid read, overflow in array size"))?;
+    // Copied as bytes, since the source is not required to be aligned
+    ptr::copy_nonoverlapping(data_ptr as *const u8, vec.as_mut_ptr() as *mut u8, size);
+    Ok(vec)
+}
+
 unsafe fn read_array_from_process<T>(
     process: HANDLE,
     data_ptr: *const T,
@@ -109,35 +358,1172 @@ where
     let size = mem::size_of::<T>()
         .checked_mul(count)
         .ok_or(anyhow!("invalid read, overflow in array size"))?;
-    unsafe {
-        ReadProcessMemory(
-            process,
-            data_ptr as *mut _,
-            vec.as_mut_ptr() as *mut _,
+    read_process(
+        process,
+        data_ptr as *const _,
+        vec.as_mut_ptr() as *mut _,
+        size,
+    )?;
+    Ok(vec)
+}
+
+/// Details of a read from another process that returned fewer bytes than
+/// requested, reported as [`Error::MemoryRead`](crate::Error::MemoryRead), for
+/// instance because the read crossed into an unmapped page. The
+/// bytes before `address + read` were read successfully, so that callers such
+/// as scans can retry with a smaller range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialRead {
+    /// Address the read started at.
+    pub address: usize,
+    /// Number of bytes actually read.
+    pub read: usize,
+    /// Number of bytes requested.
+    pub size: usize,
+}
+
+impl std::fmt::Display for PartialRead {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "error reading memory of remote process at {:#x}, read {} out of {} bytes",
+            self.address, self.read, self.size
+        )
+    }
+}
+
+impl std::error::Error for PartialRead {}
+
+/// Fills `size` bytes of `buffer` with the memory of a process, starting at
+/// `address`. Reads only partially completed, such as the ones crossing into
+/// an unreadable page, are reported as an [`Error::MemoryRead`] along with the
+/// number of bytes obtained.
+unsafe fn read_process(
+    process: HANDLE,
+    address: *const c_void,
+    buffer: *mut c_void,
+    size: usize,
+) -> Result<()> {
+    let mut read = 0;
+    let success = ReadProcessMemory(process, address, buffer, size, Some(&mut read)).as_bool();
+    ensure!(
+        read == size,
+        Error::MemoryRead(PartialRead {
+            address: address as usize,
+            read,
+            size
+        })
+    );
+    ensure!(
+        success,
+        "error reading memory of remote process at {:?}",
+        address
+    );
+    Ok(())
+}
+
+/// Reads an array of elements from a file, at the offset `data_ptr`
+/// translates to.
+unsafe fn read_array_from_file<T>(
+    file: &FileMemory,
+    data_ptr: *const T,
+    count: usize,
+) -> Result<Vec<T>>
+where
+    T: Clone + Default,
+{
+    let mut vec = vec![Default::default(); count];
+    let size = mem::size_of::<T>()
+        .checked_mul(count)
+        .ok_or(anyhow!("invalid read, overflow in array size"))?;
+    let offset = file.offset(data_ptr as u64, size)?;
+    read_file(file.handle, offset, vec.as_mut_ptr() as *mut _, size)?;
+    Ok(vec)
+}
+
+/// Fills `size` bytes of `buffer` with the contents of a file, starting at
+/// `offset`. Reads past the end of the file are reported as errors.
+unsafe fn read_file(file: HANDLE, offset: u64, buffer: *mut c_void, size: usize) -> Result<()> {
+    let offset = i64::try_from(offset).context("file offset out of range")?;
+    let size = u32::try_from(size).context("file read too large")?;
+    ensure!(
+        SetFilePointerEx(file, offset, None, FILE_BEGIN).as_bool(),
+        "error seeking to offset {:#x} of file",
+        offset
+    );
+    let mut read = 0;
+    ensure!(
+        ReadFile(file, Some(buffer), size, Some(&mut read), None).as_bool(),
+        "error reading file"
+    );
+    ensure!(
+        read == size,
+        "unexpected end of file, read {} out of {} bytes",
+        read,
+        size
+    );
+    Ok(())
+}
+
+/// Overwrites an object in the resource pointed by the given [`MemoryHandle`]
+/// with a copy of `value`. Only live memory can be written to, that is, the
+/// current process or another one opened with write access.
+///
+/// # Safety
+///
+/// The `data_ptr` argument is expected to point to a valid instance of the
+/// specified type, which the target keeps working with after being modified.
+/// The caller is responsible for the consistency of the written value with
+/// the rest of the target state.
+pub unsafe fn write<T>(memory: &MemoryHandle, data_ptr: *mut T, value: &T) -> Result<()> {
+    ensure!(!data_ptr.is_null(), "attempted to write to a null pointer");
+    match memory {
+        MemoryHandle::Own => {
+            // Copied as bytes, since the destination is not required to be
+            // aligned and the value is not moved out of its owner
+            ptr::copy_nonoverlapping(
+                value as *const T as *const u8,
+                data_ptr as *mut u8,
+                mem::size_of::<T>(),
+            );
+            Ok(())
+        }
+        MemoryHandle::Process(handle) => write_process(
+            *handle,
+            data_ptr as *const _,
+            value as *const T as *const _,
+            mem::size_of::<T>(),
+        ),
+        _ => bail!("writing to {} memory is not supported", memory.kind()),
+    }
+}
+
+/// Same as [`write`], for arrays of elements.
+///
+/// # Safety
+///
+/// The `data_ptr` argument is expected to point to `values.len()` valid
+/// instances of the specified type, see [`write`].
+pub unsafe fn write_array<T>(memory: &MemoryHandle, data_ptr: *mut T, values: &[T]) -> Result<()> {
+    ensure!(!data_ptr.is_null(), "attempted to write to a null pointer");
+    // Slices cannot overflow in size, unlike the counts given to copy_array
+    let size = mem::size_of_val(values);
+    match memory {
+        MemoryHandle::Own => {
+            ptr::copy_nonoverlapping(values.as_ptr() as *const u8, data_ptr as *mut u8, size);
+            Ok(())
+        }
+        MemoryHandle::Process(handle) => write_process(
+            *handle,
+            data_ptr as *const _,
+            values.as_ptr() as *const _,
             size,
-            None,
+        ),
+        _ => bail!("writing to {} memory is not supported", memory.kind()),
+    }
+}
+
+/// Writes `size` bytes of `buffer` to the memory of a process, starting at
+/// `address`. Writes only partially completed are reported along with the
+/// number of bytes written.
+unsafe fn write_process(
+    process: HANDLE,
+    address: *const c_void,
+    buffer: *const c_void,
+    size: usize,
+) -> Result<()> {
+    let mut written = 0;
+    let success = WriteProcessMemory(process, address, buffer, size, Some(&mut written)).as_bool();
+    ensure!(
+        written == size,
+        "error writing memory of remote process at {:?}, wrote {} out of {} bytes",
+        address,
+        written,
+        size
+    );
+    ensure!(
+        success,
+        "error writing memory of remote process at {:?}",
+        address
+    );
+    Ok(())
+}
+
+/// Granularity of memory protection, used to keep string reads from crossing
+/// into pages past the end of a string, which may not be readable.
+const PAGE_SIZE: usize = 0x1000;
+
+/// Error returned by [`read_cstr`] and [`read_wstr`] when no terminating NUL
+/// is found within the given cap, as opposed to failing to read the memory.
+/// The characters read up to the cap are kept, so that callers can decide
+/// whether to use them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringTruncated {
+    /// Characters read before reaching the cap.
+    pub partial: String,
+    /// Maximum number of characters that were to be read.
+    pub max: usize,
+}
+
+impl std::fmt::Display for StringTruncated {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "no string terminator found within {} characters",
+            self.max
         )
     }
-    .as_bool()
-    .then_some(vec)
-    .ok_or(anyhow!("error reading memory of remote process"))
 }
 
+impl std::error::Error for StringTruncated {}
+
+/// Reads a NUL-terminated string of bytes of up to `max` bytes, decoded as
+/// UTF-8 (invalid sequences are replaced). If no NUL is found within `max`
+/// bytes, a [`StringTruncated`] error is returned.
+///
+/// # Safety
+///
+/// The `data_ptr` argument is expected to point to a string.
+pub unsafe fn read_cstr(memory: &MemoryHandle, data_ptr: *const u8, max: usize) -> Result<String> {
+    let (data, terminated) = read_terminated(memory, data_ptr, max)?;
+    let string = String::from_utf8_lossy(&data).into_owned();
+    ensure!(
+        terminated,
+        StringTruncated {
+            partial: string,
+            max,
+        }
+    );
+    Ok(string)
+}
+
+/// Same as [`read_cstr`], but for strings of UTF-16 code units, with a cap of
+/// `max_chars` units.
+///
+/// # Safety
+///
+/// The `data_ptr` argument is expected to point to a string.
+pub unsafe fn read_wstr(
+    memory: &MemoryHandle,
+    data_ptr: *const u16,
+    max_chars: usize,
+) -> Result<String> {
+    let (data, terminated) = read_terminated(memory, data_ptr, max_chars)?;
+    let string = String::from_utf16_lossy(&data);
+    ensure!(
+        terminated,
+        StringTruncated {
+            partial: string,
+            max: max_chars,
+        }
+    );
+    Ok(string)
+}
+
+/// Reads up to `max` elements until a zero one, which is not included. Reads
+/// are split at page boundaries, so that no page past the terminator is read.
+/// Returns whether the terminator was found.
+unsafe fn read_terminated<T>(
+    memory: &MemoryHandle,
+    data_ptr: *const T,
+    max: usize,
+) -> Result<(Vec<T>, bool)>
+where
+    T: Copy + Default + PartialEq,
+{
+    let mut data = Vec::new();
+    while data.len() < max {
+        let address = data_ptr.add(data.len());
+        let to_page_end = (PAGE_SIZE - address as usize % PAGE_SIZE) / mem::size_of::<T>();
+        let count = to_page_end.max(1).min(max - data.len());
+        let chunk = copy_array(memory, address, count)?;
+        if let Some(end) = chunk.iter().position(|&unit| unit == T::default()) {
+            data.extend_from_slice(&chunk[..end]);
+            return Ok((data, true));
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok((data, false))
+}
+
+/// Maximum number of bytes read at once by [`search`].
+const SEARCH_CHUNK_SIZE: usize = 1 << 20;
+
 /// Searches a pattern of bytes in-memory, starting from the `base` address up
 /// to `size` bytes, returning the first coincidence. If the pattern is found,
 /// the index of the starting byte of the sequence is returned.
-pub fn search(
+///
+/// # Safety
+///
+/// For [`MemoryHandle::Own`], the `size` bytes at `base` are read directly,
+/// so they must be readable memory of the current process. Other kinds of
+/// memory check their reads, and are safe to search anywhere.
+pub unsafe fn search(
     pattern: &[u8],
     memory: &MemoryHandle,
     base: *const c_void,
     size: u32,
 ) -> Result<Option<usize>> {
-    match memory {
-        MemoryHandle::Process(_) | MemoryHandle::File(_) | MemoryHandle::Kernel(_) => {
-            let data: Vec<u8> = unsafe { copy_array(memory, base as *const _, size as usize) }
-                .context("failed to copy haystack")?;
-            Ok(memmem::find(&data, pattern))
+    search_masked(&exact(pattern), memory, base, size)
+}
+
+/// Same as [`search`], but the indexes of every coincidence are returned, in
+/// ascending order. Useful when a pattern is not unique enough to identify a
+/// location on its own, so that each candidate can be validated.
+///
+/// # Safety
+///
+/// See [`search`].
+pub unsafe fn search_all(
+    pattern: &[u8],
+    memory: &MemoryHandle,
+    base: *const c_void,
+    size: u32,
+) -> Result<Vec<usize>> {
+    search_all_masked(&exact(pattern), memory, base, size)
+}
+
+/// Same as [`search`], but `None` bytes of the pattern are wildcards, matching
+/// any byte. This way, signatures can skip operands that change between
+/// builds, such as stack offsets or relative addresses.
+///
+/// # Safety
+///
+/// See [`search`].
+pub unsafe fn search_masked(
+    pattern: &[Option<u8>],
+    memory: &MemoryHandle,
+    base: *const c_void,
+    size: u32,
+) -> Result<Option<usize>> {
+    let matches = search_limited(pattern, memory, base, size, 1)?;
+    Ok(matches.first().copied())
+}
+
+/// Same as [`search_all`], but with the wildcards of [`search_masked`].
+///
+/// # Safety
+///
+/// See [`search`].
+pub unsafe fn search_all_masked(
+    pattern: &[Option<u8>],
+    memory: &MemoryHandle,
+    base: *const c_void,
+    size: u32,
+) -> Result<Vec<usize>> {
+    search_limited(pattern, memory, base, size, usize::MAX)
+}
+
+/// Same as [`search_all_masked`], but up to `max_patched` concrete bytes of a
+/// match may hold `patch` instead, such as the software breakpoints (`int3`)
+/// set by a debugger over code. Most of the concrete bytes must still match
+/// exactly, so that runs of `patch` bytes, such as the `int3` padding between
+/// functions, are not taken as matches.
+///
+/// # Safety
+///
+/// See [`search`].
+pub unsafe fn search_all_patched(
+    pattern: &[Option<u8>],
+    memory: &MemoryHandle,
+    base: *const c_void,
+    size: u32,
+    patch: u8,
+    max_patched: usize,
+) -> Result<Vec<usize>> {
+    let find = |data: &[u8]| find_all_patched(data, pattern, patch, max_patched);
+    search_with(&find, pattern.len(), memory, base, size, usize::MAX)
+}
+
+/// Pattern matching exactly the given bytes, without wildcards.
+fn exact(pattern: &[u8]) -> Vec<Option<u8>> {
+    pattern.iter().copied().map(Some).collect()
+}
+
+/// Returns the indexes of up to `limit` coincidences of a masked pattern.
+unsafe fn search_limited(
+    pattern: &[Option<u8>],
+    memory: &MemoryHandle,
+    base: *const c_void,
+    size: u32,
+    limit: usize,
+) -> Result<Vec<usize>> {
+    let find = |data: &[u8]| find_all_masked(data, pattern).collect();
+    search_with(&find, pattern.len(), memory, base, size, limit)
+}
+
+/// Finds the indexes of every match in a chunk of memory, given its data.
+type Finder<'a> = dyn Fn(&[u8]) -> Vec<usize> + 'a;
+
+/// Same as [`search_limited`], but matches of a pattern of `length` bytes are
+/// located in each chunk of memory read by `find`.
+unsafe fn search_with(
+    find: &Finder,
+    length: usize,
+    memory: &MemoryHandle,
+    base: *const c_void,
+    size: u32,
+    limit: usize,
+) -> Result<Vec<usize>> {
+    ensure!(length > 0, "cannot search for an empty pattern");
+    let (base, end) = (base as u64, base as u64 + size as u64);
+    let regions = match memory {
+        MemoryHandle::Dump(Minidump { ranges, .. })
+        | MemoryHandle::File(FileMemory {
+            ranges: Some(ranges),
+            ..
+        }) => ranges
+            .iter()
+            .map(|range| (range.start, range.end()))
+            .collect(),
+        MemoryHandle::Process(process) => readable_regions(*process, base, end),
+        MemoryHandle::Own | MemoryHandle::File(_) => vec![(base, end)],
+        MemoryHandle::Kernel(_) => bail!("searching is not supported for {} memory", memory.kind()),
+    };
+    search_regions(find, length, memory, &regions, base, end, limit)
+}
+
+/// Same as [`search_with`], but only the parts of the `base..end` region
+/// within the given `(start, end)` regions are searched.
+unsafe fn search_regions(
+    find: &Finder,
+    length: usize,
+    memory: &MemoryHandle,
+    regions: &[(u64, u64)],
+    base: u64,
+    end: u64,
+    limit: usize,
+) -> Result<Vec<usize>> {
+    let mut matches = Vec::new();
+    for &(start, stop) in regions {
+        let (start, stop) = (start.max(base), stop.min(end));
+        if start >= stop || matches.len() >= limit {
+            continue;
+        }
+        let remaining = limit - matches.len();
+        let found = search_chunks(find, length, memory, start, stop - start, remaining)?;
+        matches.extend(
+            found
+                .into_iter()
+                .map(|index| (start - base) as usize + index),
+        );
+    }
+    Ok(matches)
+}
+
+/// Returns whether the `size` bytes at `address` lie in committed and readable
+/// memory of the process, either another one or the current one, so that
+/// pointers read from the target can be told apart from garbage before
+/// following them. Other kinds of memory are taken as readable, since their
+/// reads are checked against their bounds anyway.
+pub fn is_readable(memory: &MemoryHandle, address: *const c_void, size: usize) -> bool {
+    let process = match memory {
+        MemoryHandle::Process(process) => *process,
+        MemoryHandle::Own => CURRENT_PROCESS,
+        _ => return true,
+    };
+    let start = address as u64;
+    let Some(end) = start.checked_add(size as u64) else {
+        return false;
+    };
+    !address.is_null() && readable_regions(process, start, end) == [(start, end)]
+}
+
+/// Region of the address space of a process, as reported by `VirtualQueryEx`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryRegion {
+    /// Address the region starts at.
+    pub base: u64,
+    /// Size of the region, in bytes.
+    pub size: u64,
+    /// Access protection of the pages of the region.
+    pub protection: PAGE_PROTECTION_FLAGS,
+    /// Whether the pages of the region are committed, reserved or free.
+    pub state: VIRTUAL_ALLOCATION_TYPE,
+}
+
+impl MemoryRegion {
+    /// Returns the address right past the end of the region.
+    pub fn end(&self) -> u64 {
+        self.base + self.size
+    }
+
+    /// Returns `true` if the region is committed and its pages can be read,
+    /// that is, they are neither inaccessible nor guard pages.
+    pub fn is_readable(&self) -> bool {
+        let unreadable = PAGE_NOACCESS.0 | PAGE_GUARD.0;
+        self.state == MEM_COMMIT && self.protection.0 & unreadable == 0
+    }
+}
+
+/// Queries the region of a process containing `address`, returning `None` if
+/// the address is past the end of the user address space or the process
+/// cannot be queried.
+fn query_region(process: HANDLE, address: u64) -> Option<MemoryRegion> {
+    let mut info: MEMORY_BASIC_INFORMATION = unsafe { mem::zeroed() };
+    let written = unsafe {
+        VirtualQueryEx(
+            process,
+            Some(address as *const _),
+            &mut info,
+            mem::size_of::<MEMORY_BASIC_INFORMATION>(),
+        )
+    };
+    (written != 0).then_some(MemoryRegion {
+        base: info.BaseAddress as u64,
+        size: info.RegionSize as u64,
+        protection: info.Protect,
+        state: info.State,
+    })
+}
+
+/// Lists every region of the address space of a process, from the lowest
+/// address up to the end of the user address space, including free and
+/// reserved ones. Only process memory can be listed.
+pub fn regions(memory: &MemoryHandle) -> Result<Vec<MemoryRegion>> {
+    let MemoryHandle::Process(process) = memory else {
+        bail!("regions cannot be listed for {} memory", memory.kind());
+    };
+    let mut regions: Vec<MemoryRegion> = Vec::new();
+    let mut address = 0;
+    while let Some(region) = query_region(*process, address) {
+        // Guard against regions not moving forward, which would loop forever
+        if region.size == 0 || region.end() <= address {
+            break;
         }
-        _ => unimplemented!("search not implemented for {:?}", memory),
+        address = region.end();
+        regions.push(region);
+    }
+    ensure!(!regions.is_empty(), "unable to query the process memory");
+    Ok(regions)
+}
+
+/// Same as [`regions`], keeping only the ones that can be read, see
+/// [`MemoryRegion::is_readable`].
+pub fn committed_regions(memory: &MemoryHandle) -> Result<Vec<MemoryRegion>> {
+    Ok(regions(memory)?
+        .into_iter()
+        .filter(MemoryRegion::is_readable)
+        .collect())
+}
+
+/// Returns the `(start, end)` regions of committed and readable memory of a
+/// process within `base..end`, sorted by address. Adjacent regions are merged,
+/// so that patterns crossing their boundary are found. If the memory layout
+/// cannot be queried, the whole region is returned, leaving unreadable parts
+/// to be skipped when searched.
+fn readable_regions(process: HANDLE, base: u64, end: u64) -> Vec<(u64, u64)> {
+    let mut regions = Vec::new();
+    let mut address = base;
+    while address < end {
+        let Some(region) = query_region(process, address) else {
+            debug!("Unable to query memory at {:#x}", address);
+            return vec![(base, end)];
+        };
+        if region.is_readable() {
+            let stop = region.end().min(end);
+            match regions.last_mut() {
+                Some((_, last_end)) if *last_end == address => *last_end = stop,
+                _ => regions.push((address, stop)),
+            }
+        } else {
+            trace!(
+                "Skipping unreadable region {:#x}..{:#x}",
+                address,
+                region.end()
+            );
+        }
+        address = region.end();
+    }
+    regions
+}
+
+/// Same as [`search_with`], but the region is read in chunks of at most
+/// [`SEARCH_CHUNK_SIZE`] bytes, overlapping so that no match is missed. This
+/// avoids copying large regions at once, and chunks that cannot be read, such
+/// as guard pages, are skipped instead of failing the whole search. An error
+/// is only returned if no chunk could be read.
+unsafe fn search_chunks(
+    find: &Finder,
+    length: usize,
+    memory: &MemoryHandle,
+    base: u64,
+    size: u64,
+    limit: usize,
+) -> Result<Vec<usize>> {
+    let overlap = length.saturating_sub(1);
+    let step = SEARCH_CHUNK_SIZE.saturating_sub(overlap).max(1) as u64;
+    let (mut offset, mut read_any, mut last_error) = (0, false, None);
+    let mut matches: Vec<usize> = Vec::new();
+    while offset < size {
+        let length = (size - offset).min(SEARCH_CHUNK_SIZE as u64) as usize;
+        match copy_array::<u8>(memory, (base + offset) as *const _, length) {
+            Ok(data) => {
+                read_any = true;
+                for index in find(&data) {
+                    let index = offset as usize + index;
+                    // Matches within the overlap were already found in the
+                    // previous chunk
+                    if matches.last().is_some_and(|&last| index <= last) {
+                        continue;
+                    }
+                    matches.push(index);
+                    if matches.len() >= limit {
+                        return Ok(matches);
+                    }
+                }
+            }
+            Err(error) => {
+                debug!(
+                    "Skipping unreadable chunk at {:#x}: {}",
+                    base + offset,
+                    error
+                );
+                last_error = Some(error);
+            }
+        }
+        if offset + length as u64 >= size {
+            break;
+        }
+        offset += step;
+    }
+    match last_error {
+        Some(error) if !read_any => Err(error.context("failed to copy haystack")),
+        _ => Ok(matches),
+    }
+}
+
+/// Returns the indexes of every occurrence of a masked pattern in `data`.
+/// Candidates are located by scanning for the first concrete byte of the
+/// pattern with `memchr`, so that only those positions are compared in full.
+fn find_all_masked<'a>(
+    data: &'a [u8],
+    pattern: &'a [Option<u8>],
+) -> Box<dyn Iterator<Item = usize> + 'a> {
+    if pattern.iter().all(Option::is_some) {
+        let needle: Vec<u8> = pattern.iter().flatten().copied().collect();
+        let matches: Vec<usize> = memmem::find_iter(data, &needle).collect();
+        return Box::new(matches.into_iter());
+    }
+    let Some((anchor, &Some(byte))) = pattern.iter().enumerate().find(|(_, byte)| byte.is_some())
+    else {
+        // Only wildcards, matching at every position
+        return Box::new(0..(data.len() + 1).saturating_sub(pattern.len()));
+    };
+    Box::new(
+        memchr_iter(byte, data)
+            .filter_map(move |index| index.checked_sub(anchor))
+            .filter(move |&start| {
+                data.get(start..start + pattern.len())
+                    .is_some_and(|window| {
+                        window
+                            .iter()
+                            .zip(pattern)
+                            .all(|(byte, expected)| expected.is_none() || *expected == Some(*byte))
+                    })
+            }),
+    )
+}
+
+/// Returns the indexes of every match of a masked pattern in `data`, allowing
+/// up to `max_patched` concrete bytes to hold `patch` instead, as long as they
+/// are fewer than the ones matching exactly.
+fn find_all_patched(
+    data: &[u8],
+    pattern: &[Option<u8>],
+    patch: u8,
+    max_patched: usize,
+) -> Vec<usize> {
+    let concrete = pattern.iter().flatten().count();
+    data.windows(pattern.len())
+        .enumerate()
+        .filter(|(_, window)| {
+            let mut patched = 0;
+            for (&byte, &expected) in window.iter().zip(pattern) {
+                match expected {
+                    None => {}
+                    Some(expected) if expected == byte => {}
+                    Some(_) if byte == patch => patched += 1,
+                    Some(_) => return false,
+                }
+            }
+            patched <= max_patched && patched * 2 < concrete
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Memory of a process captured in a minidump (`.dmp`) file, such as the ones
+/// written by the Task Manager. Both full memory dumps (`Memory64ListStream`)
+/// and partial ones (`MemoryListStream`) are supported.
+#[derive(Debug)]
+pub struct Minidump {
+    file: File,
+    ranges: Vec<MappedRange>,
+    modules: Vec<DumpModule>,
+    pid: Option<u32>,
+}
+
+/// Range of virtual memory backed by a file, either a [`Minidump`] or an image
+/// opened through [`MemoryHandle::open_image`].
+#[derive(Debug, Clone, Copy)]
+struct MappedRange {
+    /// Virtual address of the first byte of the range.
+    start: u64,
+    /// Number of bytes of the range.
+    size: u64,
+    /// Offset of the first byte of the range in the dump file.
+    offset: u64,
+}
+
+impl MappedRange {
+    /// Creates a range, making sure that neither its addresses nor its offsets
+    /// overflow, since they are read from untrusted files.
+    fn new(start: u64, size: u64, offset: u64) -> Result<Self> {
+        ensure!(
+            start.checked_add(size).is_some() && offset.checked_add(size).is_some(),
+            "range of {:#x} bytes at {:#x} (offset {:#x}) overflows",
+            size,
+            start,
+            offset
+        );
+        Ok(Self {
+            start,
+            size,
+            offset,
+        })
+    }
+
+    /// Returns the address right past the end of the range.
+    fn end(&self) -> u64 {
+        self.start + self.size
+    }
+}
+
+/// Image loaded in the process captured in a [`Minidump`].
+#[derive(Debug, Clone)]
+struct DumpModule {
+    /// Full path of the image.
+    path: String,
+    /// Virtual address the image is loaded at.
+    base: u64,
+    /// Size of the image in memory.
+    size: u32,
+}
+
+const MINIDUMP_SIGNATURE: u32 = 0x504d444d; // "MDMP"
+const MODULE_LIST_STREAM: u32 = 4;
+const MEMORY_LIST_STREAM: u32 = 5;
+const MEMORY64_LIST_STREAM: u32 = 9;
+const MISC_INFO_STREAM: u32 = 15;
+/// Size of the `MINIDUMP_MODULE` structure, which is packed.
+const MINIDUMP_MODULE_SIZE: u64 = 108;
+/// Flag of `MINIDUMP_MISC_INFO` telling that the process ID is valid.
+const MINIDUMP_MISC1_PROCESS_ID: u32 = 0x1;
+
+impl Minidump {
+    /// Opens a minidump file, indexing the memory ranges and modules it holds.
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        let signature = read_u32(&mut file)?;
+        ensure!(signature == MINIDUMP_SIGNATURE, "not a minidump file");
+        let _version = read_u32(&mut file)?;
+        let streams = read_u32(&mut file)?;
+        let directory = read_u32(&mut file)?;
+        let mut directory_entries = Vec::new();
+        for i in 0..streams {
+            file.seek(SeekFrom::Start(directory as u64 + i as u64 * 12))?;
+            let (stream_type, _size, rva) = (
+                read_u32(&mut file)?,
+                read_u32(&mut file)?,
+                read_u32(&mut file)?,
+            );
+            directory_entries.push((stream_type, rva as u64));
+        }
+        let (mut ranges, mut modules, mut pid) = (Vec::new(), Vec::new(), None);
+        for (stream_type, rva) in directory_entries {
+            file.seek(SeekFrom::Start(rva))?;
+            match stream_type {
+                MEMORY64_LIST_STREAM => {
+                    let count = read_u64(&mut file)?;
+                    // Memory of every range is stored back to back, starting
+                    // at the base
+                    let mut offset = read_u64(&mut file)?;
+                    for _ in 0..count {
+                        let (start, size) = (read_u64(&mut file)?, read_u64(&mut file)?);
+                        ranges.push(
+                            MappedRange::new(start, size, offset)
+                                .context("invalid range in the memory64 list stream")?,
+                        );
+                        // Cannot overflow, as checked by MappedRange::new
+                        offset += size;
+                    }
+                }
+                MEMORY_LIST_STREAM => {
+                    let count = read_u32(&mut file)?;
+                    for _ in 0..count {
+                        let start = read_u64(&mut file)?;
+                        let (size, offset) = (read_u32(&mut file)?, read_u32(&mut file)?);
+                        ranges.push(
+                            MappedRange::new(start, size as u64, offset as u64)
+                                .context("invalid range in the memory list stream")?,
+                        );
+                    }
+                }
+                MODULE_LIST_STREAM => {
+                    let count = read_u32(&mut file)?;
+                    for i in 0..count as u64 {
+                        file.seek(SeekFrom::Start(rva + 4 + i * MINIDUMP_MODULE_SIZE))?;
+                        let (base, size) = (read_u64(&mut file)?, read_u32(&mut file)?);
+                        let _checksum = read_u32(&mut file)?;
+                        let _timestamp = read_u32(&mut file)?;
+                        let name_rva = read_u32(&mut file)?;
+                        let path = read_string(&mut file, name_rva as u64)?;
+                        modules.push(DumpModule { path, base, size });
+                    }
+                }
+                MISC_INFO_STREAM => {
+                    let _size = read_u32(&mut file)?;
+                    let flags = read_u32(&mut file)?;
+                    let process_id = read_u32(&mut file)?;
+                    pid = (flags & MINIDUMP_MISC1_PROCESS_ID != 0).then_some(process_id);
+                }
+                _ => {}
+            }
+        }
+        ensure!(
+            !ranges.is_empty(),
+            "minidump does not include process memory"
+        );
+        ranges.sort_by_key(|range| range.start);
+        debug!(
+            "Minidump holds {} memory ranges and {} modules",
+            ranges.len(),
+            modules.len()
+        );
+        Ok(Self {
+            file,
+            ranges,
+            modules,
+            pid,
+        })
+    }
+
+    /// Returns the ID of the process captured, if recorded in the dump.
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// Returns the base address and size of the loaded image with the given
+    /// file name, compared case-insensitively as Windows does.
+    pub fn module(&self, name: &str) -> Option<(*const c_void, u32)> {
+        self.modules
+            .iter()
+            .find(|module| {
+                let file_name = module.path.rsplit('\\').next().unwrap_or(&module.path);
+                file_name.eq_ignore_ascii_case(name)
+            })
+            .map(|module| (module.base as *const c_void, module.size))
+    }
+
+    /// Copies `size` bytes from the virtual `address` into `buffer`. The read
+    /// must lie within a single range of the dump.
+    unsafe fn read(&self, address: u64, buffer: *mut u8, size: usize) -> Result<()> {
+        let offset = mapped_offset(&self.ranges, address, size)?;
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(slice::from_raw_parts_mut(buffer, size))
+            .context("error reading memory from the dump")
+    }
+}
+
+/// Translates a virtual address into the offset of the file holding `size`
+/// bytes from it, which must lie within a single range.
+fn mapped_offset(ranges: &[MappedRange], address: u64, size: usize) -> Result<u64> {
+    let range = ranges
+        .iter()
+        .find(|range| range.start <= address && address < range.end())
+        .ok_or(anyhow!("address {:#x} not present in the file", address))?;
+    ensure!(
+        address.saturating_add(size as u64) <= range.end(),
+        "read of {} bytes at {:#x} exceeds the mapped memory range",
+        size,
+        address
+    );
+    Ok(range.offset + (address - range.start))
+}
+
+fn read_u32(file: &mut File) -> Result<u32> {
+    let mut bytes = [0; 4];
+    file.read_exact(&mut bytes).context("truncated minidump")?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// Maximum length in bytes of a `MINIDUMP_STRING`. Strings of a dump are the
+/// paths of its modules, which are far shorter than the longest paths Windows
+/// supports, of 32767 characters.
+const MAX_DUMP_STRING: u32 = 0x10000;
+
+/// Reads a `MINIDUMP_STRING`, made of its length in bytes followed by the
+/// UTF-16 characters. The length is checked against the rest of the file
+/// before allocating anything, so that corrupted dumps cannot request huge
+/// buffers.
+fn read_string(file: &mut File, rva: u64) -> Result<String> {
+    let file_size = file.metadata()?.len();
+    file.seek(SeekFrom::Start(rva))?;
+    let length = read_u32(file)?;
+    ensure!(
+        length % 2 == 0 && length <= MAX_DUMP_STRING,
+        "invalid string length {} in minidump",
+        length
+    );
+    ensure!(rva + 4 + length as u64 <= file_size, "truncated minidump");
+    let mut bytes = vec![0; length as usize];
+    file.read_exact(&mut bytes).context("truncated minidump")?;
+    let characters: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    Ok(String::from_utf16_lossy(&characters))
+}
+
+fn read_u64(file: &mut File) -> Result<u64> {
+    let mut bytes = [0; 8];
+    file.read_exact(&mut bytes).context("truncated minidump")?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Base address and size of a buffer, as searched by the functions above.
+    fn span(data: &[u8]) -> (*const c_void, u32) {
+        (data.as_ptr() as *const c_void, data.len() as u32)
+    }
+
+    #[test]
+    fn copy_from_own_memory() {
+        let data: Vec<u8> = (0..16).collect();
+        let memory = MemoryHandle::Own;
+        // Reads need no alignment
+        let value = unsafe { copy(&memory, data.as_ptr().add(1) as *const u32) }.unwrap();
+        assert_eq!(value, u32::from_le_bytes([1, 2, 3, 4]));
+        let values = unsafe { copy_array(&memory, data.as_ptr().add(3) as *const u16, 2) }.unwrap();
+        assert_eq!(values, [0x0403, 0x0605]);
+        assert!(unsafe { copy(&memory, ptr::null::<u32>()) }.is_err());
+    }
+
+    #[test]
+    fn write_to_own_memory() {
+        let mut data = [0u8; 8];
+        let memory = MemoryHandle::Own;
+        unsafe { write(&memory, data.as_mut_ptr().add(1) as *mut u16, &0x0201) }.unwrap();
+        unsafe { write_array(&memory, data.as_mut_ptr().add(4), &[3, 4]) }.unwrap();
+        assert_eq!(data, [0, 1, 2, 0, 3, 4, 0, 0]);
+    }
+
+    #[test]
+    fn read_strings_from_own_memory() {
+        let memory = MemoryHandle::Own;
+        let string = b"WINMINE.EXE\0";
+        assert_eq!(
+            unsafe { read_cstr(&memory, string.as_ptr(), 32) }.unwrap(),
+            "WINMINE.EXE"
+        );
+        let error = unsafe { read_cstr(&memory, string.as_ptr(), 3) }.unwrap_err();
+        assert_eq!(
+            error.downcast_ref(),
+            Some(&StringTruncated {
+                partial: "WIN".into(),
+                max: 3,
+            })
+        );
+        let wide: Vec<u16> = "Minesweeper.exe\0".encode_utf16().collect();
+        assert_eq!(
+            unsafe { read_wstr(&memory, wide.as_ptr(), 32) }.unwrap(),
+            "Minesweeper.exe"
+        );
+    }
+
+    #[test]
+    fn search_own_memory() {
+        let data = b"\x90\x8b\x0d\x01\x02\x03\x04\x90\x8b\x0d\x05\x06\x07\x08";
+        let memory = MemoryHandle::Own;
+        let (base, size) = span(data);
+        assert_eq!(
+            unsafe { search(b"\x8b\x0d", &memory, base, size) }.unwrap(),
+            Some(1)
+        );
+        assert_eq!(
+            unsafe { search_all(b"\x8b\x0d", &memory, base, size) }.unwrap(),
+            [1, 8]
+        );
+        assert_eq!(
+            unsafe { search(b"\xcc", &memory, base, size) }.unwrap(),
+            None
+        );
+        // Only the given size is searched
+        assert_eq!(
+            unsafe { search_all(b"\x8b\x0d", &memory, base, 8) }.unwrap(),
+            [1]
+        );
+        let pattern = [Some(0x0d), None, None, None, None, Some(0x90)];
+        assert_eq!(
+            unsafe { search_masked(&pattern, &memory, base, size) }.unwrap(),
+            Some(2)
+        );
+        let pattern = [Some(0x8b), Some(0x0d), None];
+        assert_eq!(
+            unsafe { search_all_masked(&pattern, &memory, base, size) }.unwrap(),
+            [1, 8]
+        );
+    }
+
+    #[test]
+    fn find_masked_patterns() {
+        let data = [1, 2, 3, 1, 2, 4, 1, 2];
+        let pattern = [Some(1), Some(2), None];
+        assert_eq!(find_all_masked(&data, &pattern).collect::<Vec<_>>(), [0, 3]);
+        // Patterns may start with wildcards
+        let pattern = [None, Some(2), Some(4)];
+        assert_eq!(find_all_masked(&data, &pattern).collect::<Vec<_>>(), [3]);
+        let pattern = [None, None];
+        assert_eq!(
+            find_all_masked(&data, &pattern).collect::<Vec<_>>(),
+            (0..7).collect::<Vec<_>>()
+        );
+        // Windows past the end of the data never match
+        let pattern = [Some(1), Some(2), None];
+        assert_eq!(find_all_masked(&data[..7], &pattern).count(), 2);
+        assert_eq!(find_all_masked(&[], &pattern).count(), 0);
+    }
+
+    #[test]
+    fn search_across_chunk_boundaries() {
+        let pattern = b"\xde\xad\xbe\xef";
+        let mut data = vec![0u8; 2 * SEARCH_CHUNK_SIZE + 16];
+        // Matches right before, across and right after the end of the first
+        // chunk, the next one starting within the one across
+        let starts = [
+            SEARCH_CHUNK_SIZE - 8,
+            SEARCH_CHUNK_SIZE - 2,
+            SEARCH_CHUNK_SIZE + 4,
+            2 * SEARCH_CHUNK_SIZE - 1,
+            data.len() - pattern.len(),
+        ];
+        for start in starts {
+            data[start..start + pattern.len()].copy_from_slice(pattern);
+        }
+        let memory = MemoryHandle::Own;
+        let (base, size) = span(&data);
+        assert_eq!(
+            unsafe { search_all(pattern, &memory, base, size) }.unwrap(),
+            starts
+        );
+        let masked = [Some(0xde), None, None, Some(0xef)];
+        assert_eq!(
+            unsafe { search_all_masked(&masked, &memory, base, size) }.unwrap(),
+            starts
+        );
+        // Searches starting past the base report indexes relative to it
+        let offset = SEARCH_CHUNK_SIZE / 2;
+        let base = unsafe { data.as_ptr().add(offset) } as *const c_void;
+        let found = unsafe { search_all(pattern, &memory, base, size - offset as u32) }.unwrap();
+        assert_eq!(found, starts.map(|start| start - offset));
+    }
+
+    #[test]
+    fn find_patched_patterns() {
+        let pattern = [Some(0x8b), Some(0x0d), None, Some(0x85), Some(0xc9)];
+        // A breakpoint over the first byte of the pattern
+        let data = [0x90, 0xcc, 0x0d, 0x00, 0x85, 0xc9];
+        assert_eq!(find_all_patched(&data, &pattern, 0xcc, 1), [1]);
+        assert_eq!(find_all_patched(&data, &pattern, 0xcc, 0), [] as [usize; 0]);
+        // Other mismatching bytes are never accepted
+        let data = [0x8b, 0x0d, 0x00, 0x84, 0xc9];
+        assert_eq!(find_all_patched(&data, &pattern, 0xcc, 1), [] as [usize; 0]);
+        // Runs of padding only matching by substitution are rejected
+        let data = [0xcc; 8];
+        assert_eq!(find_all_patched(&data, &pattern, 0xcc, 4), [] as [usize; 0]);
+        let data = [0xcc, 0xcc, 0x00, 0x85, 0xc9];
+        assert_eq!(find_all_patched(&data, &pattern, 0xcc, 4), [] as [usize; 0]);
+    }
+
+    #[test]
+    fn read_minidump_strings() {
+        let path = std::env::temp_dir().join(format!("mimisweep-{}.dmp", std::process::id()));
+        let name: Vec<u8> = "C:\\WINMINE.EXE"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let mut data = (name.len() as u32).to_le_bytes().to_vec();
+        data.extend(&name);
+        // Lengths past the end of the file, too long or odd
+        data.extend(u32::MAX.to_le_bytes());
+        data.extend(8u32.to_le_bytes());
+        data.extend(3u32.to_le_bytes());
+        std::fs::write(&path, &data).unwrap();
+        let mut file = File::open(&path).unwrap();
+        let rva = 4 + name.len() as u64;
+        assert_eq!(read_string(&mut file, 0).unwrap(), "C:\\WINMINE.EXE");
+        assert!(read_string(&mut file, rva).is_err());
+        assert!(read_string(&mut file, rva + 4).is_err());
+        assert!(read_string(&mut file, rva + 8).is_err());
+        drop(file);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reject_overflowing_minidump_ranges() {
+        let path =
+            std::env::temp_dir().join(format!("mimisweep-{}-ranges.dmp", std::process::id()));
+        let dump = |start: u64, size: u64, base: u64| {
+            let mut data = Vec::new();
+            // Header with a single stream, whose directory entry follows it
+            for value in [MINIDUMP_SIGNATURE, 0, 1, 16] {
+                data.extend(value.to_le_bytes());
+            }
+            for value in [MEMORY64_LIST_STREAM, 32, 28] {
+                data.extend(value.to_le_bytes());
+            }
+            for value in [1, base, start, size] {
+                data.extend(value.to_le_bytes());
+            }
+            data.extend([0; 16]);
+            std::fs::write(&path, data).unwrap();
+            Minidump::open(&path)
+        };
+        let minidump = dump(0x1000, 16, 60).unwrap();
+        assert_eq!(minidump.ranges[0].end(), 0x1010);
+        let error = dump(u64::MAX - 8, 16, 60).unwrap_err();
+        assert!(format!("{error:#}").contains("memory64 list stream"));
+        assert!(dump(0x1000, 16, u64::MAX - 8).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reject_empty_patterns() {
+        let data = [0xcc; 16];
+        let memory = MemoryHandle::Own;
+        let (base, size) = span(&data);
+        assert!(unsafe { search(&[], &memory, base, size) }.is_err());
+        assert!(unsafe { search_masked(&[], &memory, base, size) }.is_err());
+        assert!(unsafe { search_all_patched(&[], &memory, base, size, 0xcc, 1) }.is_err());
+    }
+
+    #[test]
+    fn search_patched_own_memory() {
+        let pattern = [Some(0x8b), Some(0x0d), None, Some(0x85), Some(0xc9)];
+        let mut data = vec![0xcc; SEARCH_CHUNK_SIZE + 16];
+        let start = SEARCH_CHUNK_SIZE - 2;
+        data[start..start + 5].copy_from_slice(&[0xcc, 0x0d, 0x00, 0x85, 0xc9]);
+        let memory = MemoryHandle::Own;
+        let (base, size) = span(&data);
+        assert_eq!(
+            unsafe { search_all_patched(&pattern, &memory, base, size, 0xcc, 1) }.unwrap(),
+            [start]
+        );
     }
 }
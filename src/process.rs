@@ -1,22 +1,104 @@
 //! Tools for interaction with Windows processes.
 
-use super::memory::{self, MemoryHandle};
+use super::memory::{self, MemoryHandle, Minidump, StringTruncated};
+use super::versions::{windows_10 as win10, windows_7 as win7, windows_xp as winxp};
+use super::{Error, Version};
 
-use anyhow::{anyhow, ensure, Result};
-use log::trace;
-use std::{ffi::c_void, mem};
-use sysinfo::{PidExt, ProcessExt, System, SystemExt};
-use windows::Win32::System::SystemInformation::IMAGE_FILE_MACHINE_I386;
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use log::{debug, trace};
+use std::{
+    cmp::Ordering,
+    ffi::c_void,
+    fmt, mem,
+    ops::Range,
+    path::Path,
+    sync::{Mutex, MutexGuard, PoisonError, TryLockError},
+    thread,
+    time::Duration,
+};
+use sysinfo::{Pid, PidExt, ProcessExt, ProcessRefreshKind, RefreshKind, System, SystemExt};
+use windows::core::{s, w};
+use windows::Win32::Security::{
+    AdjustTokenPrivileges, LookupPrivilegeValueW, LUID_AND_ATTRIBUTES, SE_DEBUG_NAME,
+    SE_PRIVILEGE_ENABLED, TOKEN_ADJUST_PRIVILEGES, TOKEN_PRIVILEGES, TOKEN_QUERY,
+};
+use windows::Win32::System::LibraryLoader::{GetModuleHandleW, GetProcAddress};
+use windows::Win32::System::SystemInformation::{
+    GetNativeSystemInfo, IMAGE_FILE_MACHINE, IMAGE_FILE_MACHINE_AMD64, IMAGE_FILE_MACHINE_ARM64,
+    IMAGE_FILE_MACHINE_I386, IMAGE_FILE_MACHINE_UNKNOWN, SYSTEM_INFO,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, GetClassNameW, GetWindowThreadProcessId,
+};
 #[allow(unused_imports)]
 use windows::Win32::{
     Foundation::*,
     System::{Diagnostics::Debug::*, Kernel::*, SystemServices::*, Threading::*},
 };
 
-/// PEB definition that overrides windows' [`PEB`] struct based on WinDbg's symbols.
+/// Process Environment Block of a process, as returned by [`peb`].
+// Only read once per process, so the size of the native variant is not boxed
+#[allow(clippy::large_enum_variant)]
+pub enum Peb {
+    /// PEB of a process of the same architecture as this one.
+    Native(Peb64),
+    /// 32-bit PEB of a process running under WOW64.
+    Wow64(Peb32),
+}
+
+impl Peb {
+    /// Address where the main image of the process is loaded.
+    pub fn image_base_address(&self) -> *const c_void {
+        match self {
+            Peb::Native(peb) => peb.image_base_address,
+            Peb::Wow64(peb) => peb.image_base_address as *const c_void,
+        }
+    }
+
+    /// Whether a debugger is attached to the process.
+    pub fn being_debugged(&self) -> bool {
+        match self {
+            Peb::Native(peb) => peb.being_debugged != 0,
+            Peb::Wow64(peb) => peb.being_debugged != 0,
+        }
+    }
+
+    /// Version of Windows the process runs on, as its major and minor version
+    /// and its build number.
+    pub fn os_version(&self) -> (u32, u32, u16) {
+        match self {
+            Peb::Native(peb) => (
+                peb.os_major_version,
+                peb.os_minor_version,
+                peb.os_build_number,
+            ),
+            Peb::Wow64(peb) => (
+                peb.os_major_version,
+                peb.os_minor_version,
+                peb.os_build_number,
+            ),
+        }
+    }
+
+    /// Terminal Services session the process runs in.
+    pub fn session_id(&self) -> u32 {
+        match self {
+            Peb::Native(peb) => peb.session_id,
+            Peb::Wow64(peb) => peb.session_id,
+        }
+    }
+}
+
+/// PEB definition that overrides windows' [`PEB`] struct based on WinDbg's symbols,
+/// up to the `SessionId` field, with the layout of Windows 7 and later.
+///
+/// Only `image_base_address`, `ldr` and `process_parameters` are relied upon
+/// by the crate, and their targets are validated when followed. The rest of the
+/// fields are read as is, on a best-effort basis: their meaning may change
+/// between Windows releases.
 #[repr(C)]
 #[allow(missing_docs)]
-pub struct Peb {
+pub struct Peb64 {
     pub inherited_address_space: u8,
     pub read_image_file_exec_options: u8,
     pub being_debugged: u8,
@@ -25,10 +107,127 @@ pub struct Peb {
     pub image_base_address: *mut c_void,
     pub ldr: *mut PEB_LDR_DATA,
     pub process_parameters: *mut RTL_USER_PROCESS_PARAMETERS,
-    // ...
+    pub sub_system_data: *mut c_void,
+    pub process_heap: *mut c_void,
+    pub fast_peb_lock: *mut c_void,
+    pub atl_thunk_slist_ptr: *mut c_void,
+    pub ifeo_key: *mut c_void,
+    pub cross_process_flags: u32,
+    pub padding1: [u8; 4],
+    pub kernel_callback_table: *mut c_void,
+    pub system_reserved: u32,
+    pub atl_thunk_slist_ptr32: u32,
+    pub api_set_map: *mut c_void,
+    pub tls_expansion_counter: u32,
+    pub padding2: [u8; 4],
+    pub tls_bitmap: *mut c_void,
+    pub tls_bitmap_bits: [u32; 2],
+    pub read_only_shared_memory_base: *mut c_void,
+    pub shared_data: *mut c_void,
+    pub read_only_static_server_data: *mut c_void,
+    pub ansi_code_page_data: *mut c_void,
+    pub oem_code_page_data: *mut c_void,
+    pub unicode_case_table_data: *mut c_void,
+    pub number_of_processors: u32,
+    pub nt_global_flag: u32,
+    pub critical_section_timeout: i64,
+    pub heap_segment_reserve: u64,
+    pub heap_segment_commit: u64,
+    pub heap_decommit_total_free_threshold: u64,
+    pub heap_decommit_free_block_threshold: u64,
+    pub number_of_heaps: u32,
+    pub maximum_number_of_heaps: u32,
+    pub process_heaps: *mut c_void,
+    pub gdi_shared_handle_table: *mut c_void,
+    pub process_starter_helper: *mut c_void,
+    pub gdi_dc_attribute_list: u32,
+    pub padding3: [u8; 4],
+    pub loader_lock: *mut c_void,
+    pub os_major_version: u32,
+    pub os_minor_version: u32,
+    pub os_build_number: u16,
+    pub os_csd_version: u16,
+    pub os_platform_id: u32,
+    pub image_subsystem: u32,
+    pub image_subsystem_major_version: u32,
+    pub image_subsystem_minor_version: u32,
+    pub padding4: [u8; 4],
+    pub active_process_affinity_mask: u64,
+    pub gdi_handle_buffer: [u32; 60],
+    pub post_process_init_routine: *mut c_void,
+    pub tls_expansion_bitmap: *mut c_void,
+    pub tls_expansion_bitmap_bits: [u32; 32],
+    pub session_id: u32,
 }
 
-/// BitField field of the [`Peb`] structure.
+/// Same as [`Peb64`], but with the 32-bit wide pointers of the PEB of WOW64
+/// processes.
+#[repr(C)]
+#[allow(missing_docs)]
+pub struct Peb32 {
+    pub inherited_address_space: u8,
+    pub read_image_file_exec_options: u8,
+    pub being_debugged: u8,
+    pub bit_field: u8,
+    pub mutant: u32,
+    pub image_base_address: u32,
+    pub ldr: u32,
+    pub process_parameters: u32,
+    pub sub_system_data: u32,
+    pub process_heap: u32,
+    pub fast_peb_lock: u32,
+    pub atl_thunk_slist_ptr: u32,
+    pub ifeo_key: u32,
+    pub cross_process_flags: u32,
+    pub kernel_callback_table: u32,
+    pub system_reserved: u32,
+    pub atl_thunk_slist_ptr32: u32,
+    pub api_set_map: u32,
+    pub tls_expansion_counter: u32,
+    pub tls_bitmap: u32,
+    pub tls_bitmap_bits: [u32; 2],
+    pub read_only_shared_memory_base: u32,
+    pub shared_data: u32,
+    pub read_only_static_server_data: u32,
+    pub ansi_code_page_data: u32,
+    pub oem_code_page_data: u32,
+    pub unicode_case_table_data: u32,
+    pub number_of_processors: u32,
+    pub nt_global_flag: u32,
+    pub padding0: [u8; 4],
+    pub critical_section_timeout: i64,
+    pub heap_segment_reserve: u32,
+    pub heap_segment_commit: u32,
+    pub heap_decommit_total_free_threshold: u32,
+    pub heap_decommit_free_block_threshold: u32,
+    pub number_of_heaps: u32,
+    pub maximum_number_of_heaps: u32,
+    pub process_heaps: u32,
+    pub gdi_shared_handle_table: u32,
+    pub process_starter_helper: u32,
+    pub gdi_dc_attribute_list: u32,
+    pub loader_lock: u32,
+    pub os_major_version: u32,
+    pub os_minor_version: u32,
+    pub os_build_number: u16,
+    pub os_csd_version: u16,
+    pub os_platform_id: u32,
+    pub image_subsystem: u32,
+    pub image_subsystem_major_version: u32,
+    pub image_subsystem_minor_version: u32,
+    pub active_process_affinity_mask: u32,
+    pub gdi_handle_buffer: [u32; 34],
+    pub post_process_init_routine: u32,
+    pub tls_expansion_bitmap: u32,
+    pub tls_expansion_bitmap_bits: [u32; 32],
+    pub session_id: u32,
+}
+
+// The sizes follow from the offset of `SessionId`, 0x2c0 and 0x1d4 respectively
+const _: () = assert!(mem::size_of::<Peb64>() == 0x2c8);
+const _: () = assert!(mem::size_of::<Peb32>() == 0x1d8);
+
+/// BitField field of the [`Peb64`] structure.
 #[repr(C)]
 #[allow(missing_docs)]
 pub struct BitField {
@@ -36,29 +235,689 @@ pub struct BitField {
     pub spare_bits: u8,
 }
 
-/// Given an **exact** process name, it returns its PID, if available.
+/// Image names of the supported versions of the game. They are listed in order
+/// of precedence, so the same game is always picked when more than one runs.
+const IMAGE_NAMES: [(&str, Version); 3] = [
+    ("Minesweeper.exe", Version::Windows7),
+    ("WINMINE.EXE", Version::WindowsXP),
+    (win10::IMAGE_NAME, Version::Windows10),
+];
+
+/// Architecture of a process image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Architecture {
+    /// 32-bit x86 image.
+    X86,
+    /// 64-bit x86-64 image.
+    X64,
+}
+
+/// Target process of the game, along with the metadata resolved once when
+/// opening it and shared by every later read.
+pub struct ProcessContext {
+    /// Handle to the memory of the process.
+    pub handle: MemoryHandle,
+    /// Process identifier.
+    pub pid: u32,
+    /// Version of the game running in the process.
+    pub version: Version,
+    /// Address where the main image is loaded.
+    pub image_base: *const c_void,
+    /// Size of the main image once loaded.
+    pub image_size: u32,
+    /// Architecture of the main image.
+    pub arch: Architecture,
+    /// Whether a debugger was attached to the process when opened. Always
+    /// `false` for contexts not backed by a live process.
+    pub being_debugged: bool,
+}
+
+impl ProcessContext {
+    /// Searches the running processes for a known game version, opening the
+    /// first one found. See [`find_game`] for details on the detection.
+    ///
+    /// Fails if several processes run the detected version, listing their
+    /// PIDs, since picking one of them would be arbitrary. One of them can be
+    /// selected with [`ProcessContext::open_pid`] instead.
+    pub fn detect() -> Result<ProcessContext> {
+        debug!("Opening Minesweeper process");
+        let Some((_, version)) = find_game() else {
+            return Err(Error::NoGameFound(None).into());
+        };
+        Self::detect_version(version)
+    }
+
+    /// Same as [`ProcessContext::detect`], but only processes running the
+    /// given version are considered, skipping the detection of the version.
+    pub fn detect_version(version: Version) -> Result<ProcessContext> {
+        Self::open(game_pid(version)?, version)
+    }
+
+    /// Waits for a known game version to be running, searching the running
+    /// processes every `poll` interval, and then opens it.
+    pub fn wait_for_game(poll: Duration) -> Result<ProcessContext> {
+        debug!("Waiting for a Minesweeper process");
+        let (pid, version) = loop {
+            if let Some(game) = find_game() {
+                break game;
+            }
+            trace!("No Minesweeper process yet, retrying in {:?}", poll);
+            thread::sleep(poll);
+        };
+        Self::open(pid, version)
+    }
+
+    /// Opens the process with the given PID, which is expected to run the
+    /// given version of the game. Only the access needed to read the game is
+    /// requested.
+    pub fn open(pid: u32, version: Version) -> Result<ProcessContext> {
+        Self::open_with_access(pid, version, READ_ACCESS)
+    }
+
+    /// Same as [`ProcessContext::open`], but write access to the memory of
+    /// the process is requested as well, as needed to modify the game, such as
+    /// by [`reveal_mines`](crate::reveal_mines).
+    pub fn open_writable(pid: u32, version: Version) -> Result<ProcessContext> {
+        Self::open_with_access(pid, version, READ_ACCESS | PROCESS_VM_WRITE)
+    }
+
+    fn open_with_access(
+        pid: u32,
+        version: Version,
+        access: PROCESS_ACCESS_RIGHTS,
+    ) -> Result<ProcessContext> {
+        trace!("Minesweeper PID: {pid}");
+        // Best-effort, the privilege is only needed for processes of other
+        // users, sessions or higher integrity levels
+        if let Err(error) = enable_debug_privilege() {
+            debug!("Unable to enable the debug privilege: {:#}", error);
+        }
+        let handle = open(pid, access)?;
+        debug!("Accessing Minesweeper's PEB");
+        let machines = architecture(&handle).context("unable to query the process architecture")?;
+        trace!("Process architecture: {:?}", machines);
+        let is_wow = machines.is_wow64();
+        let peb = peb(&handle, is_wow).context("unable to access process' PEB")?;
+        let image_base = peb.image_base_address();
+        trace!("PEB Image Base address: {:#?}", image_base);
+        let (major, minor, build) = peb.os_version();
+        trace!("Windows version: {major}.{minor}.{build}");
+        let being_debugged = peb.being_debugged();
+        if being_debugged {
+            debug!("A debugger is attached to the process");
+        }
+        let nt_headers = unsafe { nt_headers(&handle, image_base) }
+            .context("unable to access process' NT header")?;
+        let machine = nt_headers.file_header().Machine;
+        ensure!(
+            machine == IMAGE_FILE_MACHINE_I386 || machine == IMAGE_FILE_MACHINE_AMD64,
+            Error::UnsupportedArch(machine.0)
+        );
+        let (image_size, arch) = match nt_headers {
+            ImageNtHeaders::X86(headers) => (headers.OptionalHeader.SizeOfImage, Architecture::X86),
+            ImageNtHeaders::X64(headers) => (headers.OptionalHeader.SizeOfImage, Architecture::X64),
+        };
+        trace!("Image size: {:#x}, architecture: {:?}", image_size, arch);
+        *lock_last_target() = Some(TargetInfo {
+            pid,
+            version,
+            image_base: image_base as usize,
+            image_size,
+            arch,
+            addresses: Vec::new(),
+        });
+        Ok(ProcessContext {
+            handle,
+            pid,
+            version,
+            image_base,
+            image_size,
+            arch,
+            being_debugged,
+        })
+    }
+
+    /// Opens the process with the given PID, detecting the game version from
+    /// its image name, or the class of its window if the executable was
+    /// renamed. Fails if it is not a supported version of the game.
+    pub fn open_pid(pid: u32) -> Result<ProcessContext> {
+        let version = version_by_pid(pid)?;
+        debug!("Detected {} version running", version);
+        Self::open(pid, version)
+    }
+
+    /// Returns whether the game process is still running. Always `false` for
+    /// contexts not backed by a live process, such as minidumps.
+    pub fn is_running(&self) -> bool {
+        let MemoryHandle::Process(process) = self.handle else {
+            return false;
+        };
+        let mut exit_code = 0;
+        unsafe { GetExitCodeProcess(process, &mut exit_code) }.as_bool()
+            && exit_code == STILL_ACTIVE.0 as u32
+    }
+
+    /// Opens a minidump of a game process, detecting the version of the game
+    /// from the images loaded in it. The process ID is reported as 0 if not
+    /// recorded in the dump.
+    pub fn from_dump(path: &Path) -> Result<ProcessContext> {
+        debug!("Opening Minesweeper dump");
+        let dump = Minidump::open(path)?;
+        let (version, image_base, image_size) = IMAGE_NAMES
+            .into_iter()
+            .chain(super::custom_versions())
+            .find_map(|(name, version)| {
+                let (image_base, image_size) = dump.module(name)?;
+                Some((version, image_base, image_size))
+            })
+            .context("no minesweeper image in the dump")?;
+        let pid = dump.pid().unwrap_or_default();
+        debug!("Detected {} version in the dump", version);
+        let handle = MemoryHandle::Dump(dump);
+        let arch = match unsafe { nt_headers(&handle, image_base) }
+            .context("unable to access the NT header of the dumped image")?
+        {
+            ImageNtHeaders::X86(_) => Architecture::X86,
+            ImageNtHeaders::X64(_) => Architecture::X64,
+        };
+        trace!("Image size: {:#x}, architecture: {:?}", image_size, arch);
+        *lock_last_target() = Some(TargetInfo {
+            pid,
+            version,
+            image_base: image_base as usize,
+            image_size,
+            arch,
+            addresses: Vec::new(),
+        });
+        Ok(ProcessContext {
+            handle,
+            pid,
+            version,
+            image_base,
+            image_size,
+            arch,
+            being_debugged: false,
+        })
+    }
+}
+
+/// Diagnostic information about the last target process opened, meant to be
+/// included in bug reports.
+#[derive(Debug, Clone)]
+pub struct TargetInfo {
+    /// Process identifier.
+    pub pid: u32,
+    /// Version of the game running in the process.
+    pub version: Version,
+    /// Address where the main image is loaded.
+    pub image_base: usize,
+    /// Size of the main image once loaded.
+    pub image_size: u32,
+    /// Architecture of the main image.
+    pub arch: Architecture,
+    /// Addresses of the game structures resolved so far, by name.
+    pub addresses: Vec<(&'static str, usize)>,
+}
+
+impl fmt::Display for TargetInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "PID: {}", self.pid)?;
+        writeln!(f, "Version: {} ({:?})", self.version, self.arch)?;
+        write!(
+            f,
+            "Image: {:#x} (size {:#x})",
+            self.image_base, self.image_size
+        )?;
+        for (name, address) in &self.addresses {
+            write!(f, "\n{name}: {address:#x}")?;
+        }
+        Ok(())
+    }
+}
+
+static LAST_TARGET: Mutex<Option<TargetInfo>> = Mutex::new(None);
+
+fn lock_last_target() -> MutexGuard<'static, Option<TargetInfo>> {
+    // The diagnostics remain meaningful even if a previous holder panicked
+    LAST_TARGET.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Returns the diagnostic information of the last target process opened.
+///
+/// Meant to be called from a panic hook, so it gives up instead of blocking
+/// if the information is being updated at the same time.
+pub fn last_target() -> Option<TargetInfo> {
+    match LAST_TARGET.try_lock() {
+        Ok(target) => target.clone(),
+        Err(TryLockError::Poisoned(target)) => target.into_inner().clone(),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+/// Records the address of a game structure in the diagnostic information of
+/// the last target process opened.
+pub(crate) fn record_address(name: &'static str, address: *const c_void) {
+    if let Some(target) = lock_last_target().as_mut() {
+        target.addresses.retain(|(recorded, _)| *recorded != name);
+        target.addresses.push((name, address as usize));
+    }
+}
+
+/// Access rights requested to read the game, see [`ProcessContext::open`].
+const READ_ACCESS: PROCESS_ACCESS_RIGHTS =
+    PROCESS_ACCESS_RIGHTS(PROCESS_VM_READ.0 | PROCESS_VM_OPERATION.0 | PROCESS_QUERY_INFORMATION.0);
+
+/// Access rights enough to read the game, used by [`open`] when the desired
+/// ones are denied.
+const MINIMAL_ACCESS: PROCESS_ACCESS_RIGHTS =
+    PROCESS_ACCESS_RIGHTS(PROCESS_VM_READ.0 | PROCESS_QUERY_LIMITED_INFORMATION.0);
+
+/// Opens the process with the given PID, requesting the `desired` access
+/// rights. If they are denied, as may happen on locked-down systems, only the
+/// rights needed to read memory and query basic information are requested,
+/// which are enough to read the game. Features needing more, such as
+/// [`reveal_mines`](crate::reveal_mines) or the memory layout queries used to
+/// skip unreadable regions, then fail or degrade on their own.
+pub fn open(pid: u32, desired: PROCESS_ACCESS_RIGHTS) -> Result<MemoryHandle> {
+    let h_process = match unsafe { OpenProcess(desired, false, pid) } {
+        Ok(handle) => {
+            debug!("Opened process {} with access {:#x}", pid, desired.0);
+            handle
+        }
+        Err(error)
+            if error.code() == ERROR_ACCESS_DENIED.to_hresult() && desired != MINIMAL_ACCESS =>
+        {
+            debug!(
+                "Access {:#x} to process {} denied, retrying with {:#x}",
+                desired.0, pid, MINIMAL_ACCESS.0
+            );
+            let handle = unsafe { OpenProcess(MINIMAL_ACCESS, false, pid) }
+                .context(Error::ProcessOpen(pid))?;
+            debug!("Opened process {} with access {:#x}", pid, MINIMAL_ACCESS.0);
+            handle
+        }
+        Err(error) => return Err(error).context(Error::ProcessOpen(pid)),
+    };
+    trace!("Process handle: {:?}", h_process);
+    MemoryHandle::from_process_handle(h_process)
+}
+
+/// Lists the running processes, only loading their basic information, such as
+/// their names, instead of every detail about the system.
+fn running_processes() -> System {
+    System::new_with_specifics(RefreshKind::new().with_processes(ProcessRefreshKind::new()))
+}
+
+/// Given an **exact** process name, it returns its PID, if available. If
+/// several processes share the name, an arbitrary one is returned, see
+/// [`pids_by_name`].
 pub fn pid_by_name(process_name: &str) -> Option<u32> {
-    let system = System::new_all();
-    let mut processes = system.processes_by_exact_name(process_name);
-    (*processes).next().map(|process| process.pid().as_u32())
+    pids_by_name(process_name).into_iter().next()
+}
+
+/// Given an **exact** process name, it returns the PIDs of every process
+/// running with it, in ascending order.
+pub fn pids_by_name(process_name: &str) -> Vec<u32> {
+    let system = running_processes();
+    let mut pids: Vec<_> = system
+        .processes_by_exact_name(process_name)
+        .map(|process| process.pid().as_u32())
+        .collect();
+    pids.sort_unstable();
+    pids
+}
+
+/// Searches the running processes for a known game version, returning the PID
+/// and version of the first one found.
+///
+/// Detection only relies on identifiers that are not translated on localized
+/// Windows installations: executable image names first, then window class
+/// names. If several supported versions are running at the same time, the
+/// Windows 7 game takes precedence over the Windows XP one.
+pub fn find_game() -> Option<(u32, Version)> {
+    let (pid, version) = list_minesweeper_candidates()
+        .into_iter()
+        .next()
+        .or_else(|| {
+            debug!("No known process name found, falling back to window classes");
+            [Version::Windows7, Version::WindowsXP]
+                .into_iter()
+                .find_map(|version| Some((*pids_by_game_window(version).first()?, version)))
+        })?;
+    debug!("Detected {} version running", version);
+    Some((pid, version))
+}
+
+/// Returns the PID of the only process running the given version of the game,
+/// matching image names first and window classes otherwise. Fails if none or
+/// several of them are running.
+pub(crate) fn game_pid(version: Version) -> Result<u32> {
+    let mut pids: Vec<_> = list_minesweeper_candidates()
+        .into_iter()
+        .filter(|&(_, candidate)| candidate == version)
+        .map(|(pid, _)| pid)
+        .collect();
+    if pids.is_empty() {
+        debug!("No known process name found, falling back to window classes");
+        pids = pids_by_game_window(version);
+    }
+    match pids[..] {
+        [] => Err(Error::NoGameFound(Some(version)).into()),
+        [pid] => Ok(pid),
+        _ => Err(Error::SeveralGames { version, pids }.into()),
+    }
+}
+
+/// Returns the game version run by the process with the given PID, matching
+/// its image name first and the class of its windows otherwise.
+pub fn version_by_pid(pid: u32) -> Result<Version> {
+    let system = running_processes();
+    let process = system
+        .process(Pid::from_u32(pid))
+        .with_context(|| format!("no process with PID {pid}"))?;
+    let name = process.name();
+    if let Some(version) = version_by_image_name(name) {
+        return Ok(version);
+    }
+    debug!(
+        "Unknown image name {}, falling back to window classes",
+        name
+    );
+    [Version::Windows7, Version::WindowsXP]
+        .into_iter()
+        .find(|&version| pids_by_game_window(version).contains(&pid))
+        .with_context(|| format!("process {pid} ({name}) is not a supported Minesweeper build"))
+}
+
+/// Returns the game version whose executable has the given image name,
+/// compared case-insensitively as Windows does.
+fn version_by_image_name(name: &str) -> Option<Version> {
+    IMAGE_NAMES
+        .into_iter()
+        .chain(super::custom_versions())
+        .find(|(image_name, _)| image_name.eq_ignore_ascii_case(name))
+        .map(|(_, version)| version)
 }
 
-/// Retrieves the [`Peb`] from the given memory handle.
-pub fn peb(memory: &MemoryHandle, _is_wow: bool) -> Result<Peb> {
+/// Returns the class name of the main window of a built-in version.
+///
+/// The Windows XP and Windows 7 games register the same class, so a window
+/// of that class does not tell them apart on its own, see
+/// [`pids_by_game_window`].
+fn window_class(version: Version) -> Option<&'static str> {
+    match version {
+        Version::Windows7 => Some(win7::WINDOW_CLASS),
+        Version::WindowsXP => Some(winxp::WINDOW_CLASS),
+        Version::Windows10 | Version::Custom(_) => None,
+    }
+}
+
+/// Returns the PIDs of the processes owning a main window of the given
+/// version of the game. Since window classes are shared between versions, the
+/// version run by each process is then told from its image, see
+/// [`version_by_image`].
+fn pids_by_game_window(version: Version) -> Vec<u32> {
+    let Some(class) = window_class(version) else {
+        return Vec::new();
+    };
+    pids_by_window_class(class)
+        .into_iter()
+        .filter(|&pid| match version_by_image(pid) {
+            Ok(found) => found == version,
+            Err(error) => {
+                debug!(
+                    "Unable to inspect the image of process {}: {:#}",
+                    pid, error
+                );
+                false
+            }
+        })
+        .collect()
+}
+
+/// Tells the built-in version of the game run by the process with the given
+/// PID from the Windows release its image targets, as recorded in its
+/// optional header. Unlike its name, it is kept when the executable is
+/// renamed.
+fn version_by_image(pid: u32) -> Result<Version> {
+    let handle = open(pid, MINIMAL_ACCESS)?;
+    let is_wow = architecture(&handle)?.is_wow64();
+    let image_base = peb(&handle, is_wow)?.image_base_address();
+    let headers = unsafe { nt_headers(&handle, image_base) }?;
+    let (major, minor) = headers.os_version();
+    trace!("Process {} image targets Windows {}.{}", pid, major, minor);
+    Ok(version_by_target_os(major))
+}
+
+/// Returns the built-in version of the game targeting the given major Windows
+/// version: Windows XP is 5.x, while Windows Vista and 7 are 6.x.
+fn version_by_target_os(major: u16) -> Version {
+    match major {
+        0..=5 => Version::WindowsXP,
+        _ => Version::Windows7,
+    }
+}
+
+/// Returns the PID and game version of every running process whose image name
+/// matches a supported version of the game, in order of precedence. Versions
+/// added through [`register_version`](crate::register_version) come last.
+pub fn list_minesweeper_candidates() -> Vec<(u32, Version)> {
+    let system = running_processes();
+    let candidates: Vec<_> = IMAGE_NAMES
+        .into_iter()
+        .chain(super::custom_versions())
+        .flat_map(|(name, version)| {
+            system
+                .processes_by_exact_name(name)
+                .map(move |process| (process.pid().as_u32(), version))
+        })
+        .collect();
+    trace!("Minesweeper candidates: {:?}", candidates);
+    candidates
+}
+
+/// Given an **exact** window class name, it returns the PID of the process
+/// owning the first top-level window registered with it, if available.
+///
+/// Unlike window titles, class names are not translated on localized Windows
+/// installations, which makes them a reliable detection fallback.
+pub fn pid_by_window_class(class_name: &str) -> Option<u32> {
+    pids_by_window_class(class_name).into_iter().next()
+}
+
+/// Same as [`pid_by_window_class`], but the PIDs of every process owning a
+/// top-level window registered with the class are returned, in ascending
+/// order.
+pub fn pids_by_window_class(class_name: &str) -> Vec<u32> {
+    struct Search {
+        class_name: Vec<u16>,
+        pids: Vec<u32>,
+    }
+
+    unsafe extern "system" fn enum_window(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let search = &mut *(lparam.0 as *mut Search);
+        let mut buffer = [0_u16; 256];
+        let length = GetClassNameW(hwnd, &mut buffer) as usize;
+        if length == 0 || buffer[..length] != search.class_name[..] {
+            return TRUE;
+        }
+        let mut pid = 0_u32;
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+        search.pids.push(pid);
+        TRUE
+    }
+
+    let mut search = Search {
+        class_name: class_name.encode_utf16().collect(),
+        pids: Vec::new(),
+    };
+    unsafe { EnumWindows(Some(enum_window), LPARAM(&mut search as *mut _ as isize)) };
+    search.pids.sort_unstable();
+    search.pids.dedup();
+    trace!("PIDs for window class {}: {:?}", class_name, search.pids);
+    search.pids
+}
+
+/// Enables the `SeDebugPrivilege` of the current process, which allows opening
+/// processes of other users or sessions, or running at a higher integrity
+/// level. Only elevated processes hold the privilege, so it fails otherwise.
+pub fn enable_debug_privilege() -> Result<()> {
+    unsafe {
+        let mut token = HANDLE::default();
+        ensure!(
+            OpenProcessToken(
+                GetCurrentProcess(),
+                TOKEN_ADJUST_PRIVILEGES | TOKEN_QUERY,
+                &mut token
+            )
+            .as_bool(),
+            "failed to open the process token"
+        );
+        let result = adjust_debug_privilege(token);
+        CloseHandle(token);
+        result
+    }
+}
+
+unsafe fn adjust_debug_privilege(token: HANDLE) -> Result<()> {
+    let mut luid = LUID::default();
+    ensure!(
+        LookupPrivilegeValueW(None, SE_DEBUG_NAME, &mut luid).as_bool(),
+        "failed to look up the debug privilege"
+    );
+    let privileges = TOKEN_PRIVILEGES {
+        PrivilegeCount: 1,
+        Privileges: [LUID_AND_ATTRIBUTES {
+            Luid: luid,
+            Attributes: SE_PRIVILEGE_ENABLED,
+        }],
+    };
+    ensure!(
+        AdjustTokenPrivileges(token, false, Some(&privileges), 0, None, None).as_bool(),
+        "failed to adjust the token privileges"
+    );
+    // Succeeds even if the privilege is not held, reporting it separately
+    ensure!(
+        GetLastError() != ERROR_NOT_ALL_ASSIGNED,
+        "the debug privilege is not held by the current user"
+    );
+    trace!("Debug privilege enabled");
+    Ok(())
+}
+
+/// Returns whether the process behind the given memory handle is a 32-bit one
+/// running under WOW64.
+pub fn is_wow64(memory: &MemoryHandle) -> Result<bool> {
+    let process = memory.as_handle()?;
+    let mut is_wow = BOOL::default();
+    ensure!(
+        unsafe { IsWow64Process(process, &mut is_wow) }.as_bool(),
+        "IsWow64Process failed"
+    );
+    Ok(is_wow.as_bool())
+}
+
+/// Machines of a process and of the system running it, as returned by
+/// [`architecture`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arch {
+    /// Machine emulated by WOW64 for the process, or
+    /// `IMAGE_FILE_MACHINE_UNKNOWN` if it does not run under WOW64. Processes
+    /// emulated outside of WOW64, such as x64 ones on ARM64 systems, are not
+    /// told apart from native ones.
+    pub process: IMAGE_FILE_MACHINE,
+    /// Machine of the system.
+    pub native: IMAGE_FILE_MACHINE,
+}
+
+impl Arch {
+    /// Returns `true` if the process runs under WOW64.
+    pub fn is_wow64(&self) -> bool {
+        self.process != IMAGE_FILE_MACHINE_UNKNOWN
+    }
+}
+
+/// Signature of `IsWow64Process2`, only available since Windows 10.
+type IsWow64Process2Fn =
+    unsafe extern "system" fn(HANDLE, *mut IMAGE_FILE_MACHINE, *mut IMAGE_FILE_MACHINE) -> BOOL;
+
+/// Queries the machines of the process behind the given memory handle and of
+/// the system, see [`Arch`]. `IsWow64Process2` is looked up at runtime, as it
+/// is missing before Windows 10, where `IsWow64Process` and the processor of
+/// the system are used instead.
+pub fn architecture(memory: &MemoryHandle) -> Result<Arch> {
+    let process = memory.as_handle()?;
+    let is_wow64_process2 = unsafe {
+        GetModuleHandleW(w!("kernel32.dll"))
+            .ok()
+            .and_then(|kernel32| GetProcAddress(kernel32, s!("IsWow64Process2")))
+            .map(|function| mem::transmute::<_, IsWow64Process2Fn>(function))
+    };
+    if let Some(is_wow64_process2) = is_wow64_process2 {
+        let mut arch = Arch {
+            process: IMAGE_FILE_MACHINE_UNKNOWN,
+            native: IMAGE_FILE_MACHINE_UNKNOWN,
+        };
+        ensure!(
+            unsafe { is_wow64_process2(process, &mut arch.process, &mut arch.native) }.as_bool(),
+            "IsWow64Process2 failed"
+        );
+        return Ok(arch);
+    }
+    trace!("IsWow64Process2 is not available, falling back to IsWow64Process");
+    let mut info = SYSTEM_INFO::default();
+    unsafe { GetNativeSystemInfo(&mut info) };
+    let native = match unsafe { info.Anonymous.Anonymous.wProcessorArchitecture } {
+        PROCESSOR_ARCHITECTURE_INTEL => IMAGE_FILE_MACHINE_I386,
+        PROCESSOR_ARCHITECTURE_AMD64 => IMAGE_FILE_MACHINE_AMD64,
+        // Only defined as a plain integer by the bindings
+        architecture if architecture.0 as u32 == PROCESSOR_ARCHITECTURE_ARM64 => {
+            IMAGE_FILE_MACHINE_ARM64
+        }
+        _ => IMAGE_FILE_MACHINE_UNKNOWN,
+    };
+    // Before Windows 10, WOW64 only ever ran 32-bit x86 processes
+    let process = match is_wow64(memory)? {
+        true => IMAGE_FILE_MACHINE_I386,
+        false => IMAGE_FILE_MACHINE_UNKNOWN,
+    };
+    Ok(Arch { process, native })
+}
+
+/// Retrieves the [`Peb`] from the given memory handle. For processes running
+/// under WOW64, `is_wow` selects their 32-bit PEB instead of the native one.
+pub fn peb(memory: &MemoryHandle, is_wow: bool) -> Result<Peb> {
     match memory {
-        MemoryHandle::Process(_) => peb_process(memory, _is_wow),
-        _ => unimplemented!("PEB extraction for {:?} is not implemented", memory),
+        MemoryHandle::Own | MemoryHandle::Process(_) => peb_process(memory, is_wow),
+        _ => bail!("PEB extraction for {} is not implemented", memory.kind()),
     }
 }
 
-fn peb_process(memory: &MemoryHandle, _is_wow: bool) -> Result<Peb> {
+fn peb_process(memory: &MemoryHandle, is_wow: bool) -> Result<Peb> {
+    let process = memory.as_handle()?;
     unsafe {
+        if is_wow {
+            // The address of the 32-bit PEB is returned instead of a flag
+            let mut peb_address = 0_usize;
+            trace!("About to call NtQueryInformationProcess for the WOW64 PEB");
+            NtQueryInformationProcess(
+                process,
+                ProcessWow64Information,
+                &mut peb_address as *mut _ as _,
+                mem::size_of::<usize>() as u32,
+                std::ptr::null_mut(),
+            )?;
+            ensure!(peb_address != 0, "process is not running under WOW64");
+            trace!("WOW64 PEB address: {:#x}", peb_address);
+            let peb = memory::copy(memory, peb_address as *const Peb32)?;
+            return Ok(Peb::Wow64(peb));
+        }
         let mut return_length = 0_u32;
         let mut process_informations: PROCESS_BASIC_INFORMATION = mem::zeroed();
         let process_information_length = mem::size_of::<PROCESS_BASIC_INFORMATION>() as u32;
         trace!("About to call NtQueryInformationProcess");
         NtQueryInformationProcess(
-            **memory,
+            process,
             ProcessBasicInformation,
             &mut process_informations as *mut _ as _,
             process_information_length,
@@ -69,10 +928,235 @@ fn peb_process(memory: &MemoryHandle, _is_wow: bool) -> Result<Peb> {
             "unexpected result from NtQueryInformationProcess"
         );
         trace!("PEB address: {:?}", process_informations.PebBaseAddress);
-        memory::copy(memory, process_informations.PebBaseAddress as *const Peb)
+        let peb = memory::copy(memory, process_informations.PebBaseAddress as *const Peb64)?;
+        Ok(Peb::Native(peb))
     }
 }
 
+/// Module loaded in a process, as returned by [`modules`].
+#[derive(Debug, Clone)]
+pub struct ModuleInfo {
+    /// File name of the image, as recorded by the loader.
+    pub name: String,
+    /// Full path of the image.
+    pub path: String,
+    /// Address the image is loaded at.
+    pub base: *const c_void,
+    /// Size of the image once loaded.
+    pub size: u32,
+}
+
+/// Upper bound of the modules listed by [`modules`], guarding against loops
+/// in a corrupted module list.
+const MAX_MODULES: usize = 4096;
+
+/// `PEB_LDR_DATA` up to the `InLoadOrderModuleList` head, with the layout of
+/// 64-bit processes. List entries are given as `[Flink, Blink]`. The head is
+/// the last field, so its offset is the size of the struct minus its own.
+#[repr(C)]
+struct LdrData64 {
+    length: u32,
+    initialized: u32,
+    ss_handle: u64,
+    in_load_order_module_list: [u64; 2],
+}
+
+/// `LDR_DATA_TABLE_ENTRY` up to the `BaseDllName` field, with the layout of
+/// 64-bit processes. Links of the `InLoadOrderModuleList` point to the
+/// `in_load_order_links` field, at the start of the entry.
+#[repr(C)]
+struct LdrEntry64 {
+    in_load_order_links: [u64; 2],
+    in_memory_order_links: [u64; 2],
+    in_initialization_order_links: [u64; 2],
+    dll_base: u64,
+    entry_point: u64,
+    size_of_image: u32,
+    full_dll_name: UnicodeString64,
+    base_dll_name: UnicodeString64,
+}
+
+#[repr(C)]
+struct UnicodeString64 {
+    length: u16,
+    maximum_length: u16,
+    buffer: u64,
+}
+
+/// Same as [`LdrData64`], with the layout of WOW64 processes.
+#[repr(C)]
+struct LdrData32 {
+    length: u32,
+    initialized: u32,
+    ss_handle: u32,
+    in_load_order_module_list: [u32; 2],
+}
+
+/// Same as [`LdrEntry64`], with the layout of WOW64 processes.
+#[repr(C)]
+struct LdrEntry32 {
+    in_load_order_links: [u32; 2],
+    in_memory_order_links: [u32; 2],
+    in_initialization_order_links: [u32; 2],
+    dll_base: u32,
+    entry_point: u32,
+    size_of_image: u32,
+    full_dll_name: UnicodeString32,
+    base_dll_name: UnicodeString32,
+}
+
+#[repr(C)]
+struct UnicodeString32 {
+    length: u16,
+    maximum_length: u16,
+    buffer: u32,
+}
+
+/// Lists the modules loaded in a process by walking the
+/// `InLoadOrderModuleList` of its loader data, found through the [`Peb`]. The
+/// main image of the process comes first.
+pub fn modules(memory: &MemoryHandle) -> Result<Vec<ModuleInfo>> {
+    let is_wow = is_wow64(memory)?;
+    let peb = peb(memory, is_wow)?;
+    let mut modules = Vec::new();
+    unsafe {
+        match peb {
+            Peb::Native(peb) => {
+                let p_ldr = peb.ldr as u64;
+                let ldr: LdrData64 = memory::copy(memory, p_ldr as *const _)
+                    .context("failed to read loader data")?;
+                let head =
+                    p_ldr + (mem::size_of::<LdrData64>() - mem::size_of::<[u64; 2]>()) as u64;
+                let mut link = ldr.in_load_order_module_list[0];
+                while link != head && link != 0 {
+                    ensure!(modules.len() < MAX_MODULES, "module list is too long");
+                    let entry: LdrEntry64 = memory::copy(memory, link as *const _)
+                        .context("failed to read module entry")?;
+                    modules.push(ModuleInfo {
+                        name: entry.base_dll_name.read(memory)?,
+                        path: entry.full_dll_name.read(memory)?,
+                        base: entry.dll_base as *const c_void,
+                        size: entry.size_of_image,
+                    });
+                    link = entry.in_load_order_links[0];
+                }
+            }
+            Peb::Wow64(peb) => {
+                let p_ldr = peb.ldr;
+                let ldr: LdrData32 = memory::copy(memory, p_ldr as usize as *const _)
+                    .context("failed to read loader data")?;
+                let head =
+                    p_ldr + (mem::size_of::<LdrData32>() - mem::size_of::<[u32; 2]>()) as u32;
+                let mut link = ldr.in_load_order_module_list[0];
+                while link != head && link != 0 {
+                    ensure!(modules.len() < MAX_MODULES, "module list is too long");
+                    let entry: LdrEntry32 = memory::copy(memory, link as usize as *const _)
+                        .context("failed to read module entry")?;
+                    modules.push(ModuleInfo {
+                        name: entry.base_dll_name.read(memory)?,
+                        path: entry.full_dll_name.read(memory)?,
+                        base: entry.dll_base as usize as *const c_void,
+                        size: entry.size_of_image,
+                    });
+                    link = entry.in_load_order_links[0];
+                }
+            }
+        }
+    }
+    trace!("Loaded modules: {:?}", modules);
+    Ok(modules)
+}
+
+/// Returns the base address and size of the module with the given file name,
+/// compared case-insensitively as Windows does, or `None` if no such module is
+/// loaded. Minidumps are looked up in their own module list, and processes
+/// through [`modules`], whose failures are reported instead of being taken as
+/// a missing module.
+pub fn module_base(memory: &MemoryHandle, name: &str) -> Result<Option<(*const c_void, u32)>> {
+    if let MemoryHandle::Dump(dump) = memory {
+        return Ok(dump.module(name));
+    }
+    let modules = modules(memory).context("unable to list the loaded modules")?;
+    Ok(modules
+        .into_iter()
+        .find(|module| module.name.eq_ignore_ascii_case(name))
+        .map(|module| (module.base, module.size)))
+}
+
+/// `RTL_USER_PROCESS_PARAMETERS` up to the `CommandLine` field, with the
+/// layout of 64-bit processes.
+#[repr(C)]
+struct ProcessParameters64 {
+    reserved: [u8; 0x70],
+    command_line: UnicodeString64,
+}
+
+/// Same as [`ProcessParameters64`], with the layout of WOW64 processes.
+#[repr(C)]
+struct ProcessParameters32 {
+    reserved: [u8; 0x40],
+    command_line: UnicodeString32,
+}
+
+/// Returns the command line the process was started with, as found in the
+/// process parameters referenced by its [`Peb`].
+pub fn command_line(memory: &MemoryHandle) -> Result<String> {
+    let is_wow = is_wow64(memory)?;
+    let peb = peb(memory, is_wow)?;
+    unsafe {
+        match peb {
+            Peb::Native(peb) => {
+                let parameters: ProcessParameters64 =
+                    memory::copy(memory, peb.process_parameters as *const _)
+                        .context("failed to read process parameters")?;
+                parameters.command_line.read(memory)
+            }
+            Peb::Wow64(peb) => {
+                let parameters: ProcessParameters32 =
+                    memory::copy(memory, peb.process_parameters as usize as *const _)
+                        .context("failed to read process parameters")?;
+                parameters.command_line.read(memory)
+            }
+        }
+    }
+}
+
+impl UnicodeString64 {
+    unsafe fn read(&self, memory: &MemoryHandle) -> Result<String> {
+        read_unicode_string(memory, self.buffer, self.length, self.maximum_length)
+    }
+}
+
+impl UnicodeString32 {
+    unsafe fn read(&self, memory: &MemoryHandle) -> Result<String> {
+        read_unicode_string(memory, self.buffer as u64, self.length, self.maximum_length)
+    }
+}
+
+/// Reads the UTF-16 buffer of a `UNICODE_STRING`, whose lengths are given in
+/// bytes. Being 16-bit, they already bound the amount of data read, but they
+/// are checked to be consistent to detect garbage.
+unsafe fn read_unicode_string(
+    memory: &MemoryHandle,
+    buffer: u64,
+    length: u16,
+    maximum_length: u16,
+) -> Result<String> {
+    ensure!(
+        length & 1 == 0 && length <= maximum_length,
+        "malformed string of {} bytes out of {}",
+        length,
+        maximum_length
+    );
+    if length == 0 {
+        return Ok(String::new());
+    }
+    let data: Vec<u16> =
+        memory::copy_array(memory, buffer as usize as *const _, length as usize / 2)
+            .context("failed to read string")?;
+    Ok(String::from_utf16_lossy(&data))
+}
+
 #[repr(C)]
 struct ImageNtHeadersCommon {
     signature: u32,
@@ -95,6 +1179,98 @@ impl ImageNtHeaders {
             Self::X64(header) => header.Signature == IMAGE_NT_SIGNATURE,
         }
     }
+
+    /// Returns the requested entry of the optional header data directory.
+    pub fn data_directory(&self, entry: IMAGE_DIRECTORY_ENTRY) -> IMAGE_DATA_DIRECTORY {
+        match self {
+            Self::X86(header) => header.OptionalHeader.DataDirectory[entry.0 as usize],
+            Self::X64(header) => header.OptionalHeader.DataDirectory[entry.0 as usize],
+        }
+    }
+
+    /// Returns the address the image prefers to be loaded at, as given by the
+    /// `ImageBase` field of the optional header.
+    pub fn image_base(&self) -> u64 {
+        match self {
+            Self::X86(header) => header.OptionalHeader.ImageBase as u64,
+            Self::X64(header) => header.OptionalHeader.ImageBase,
+        }
+    }
+
+    /// Returns the major and minor version of Windows the image targets, as
+    /// given by the optional header.
+    pub fn os_version(&self) -> (u16, u16) {
+        match self {
+            Self::X86(header) => (
+                header.OptionalHeader.MajorOperatingSystemVersion,
+                header.OptionalHeader.MinorOperatingSystemVersion,
+            ),
+            Self::X64(header) => (
+                header.OptionalHeader.MajorOperatingSystemVersion,
+                header.OptionalHeader.MinorOperatingSystemVersion,
+            ),
+        }
+    }
+
+    /// Returns the file header shared by all architectures.
+    pub fn file_header(&self) -> &IMAGE_FILE_HEADER {
+        match self {
+            Self::X86(header) => &header.FileHeader,
+            Self::X64(header) => &header.FileHeader,
+        }
+    }
+}
+
+/// Entry of the section table of a PE image.
+#[derive(Debug, Clone)]
+pub struct SectionInfo {
+    /// Name of the section (e.g. `.text`), without trailing padding.
+    pub name: String,
+    /// Address of the section, relative to the image base.
+    pub virtual_address: u32,
+    /// Size of the section once mapped in memory.
+    pub virtual_size: u32,
+    /// Offset of the section data in the file.
+    pub raw_address: u32,
+    /// Size of the section data in the file.
+    pub raw_size: u32,
+    /// Flags describing the section contents and memory protection.
+    pub characteristics: IMAGE_SECTION_CHARACTERISTICS,
+}
+
+impl SectionInfo {
+    /// Range of RVAs covered by the section once mapped in memory.
+    pub fn virtual_range(&self) -> Range<u32> {
+        // The loader falls back to the raw size if no virtual size is present
+        let size = match self.virtual_size {
+            0 => self.raw_size,
+            size => size,
+        };
+        self.virtual_address..self.virtual_address.saturating_add(size)
+    }
+
+    /// Range of file offsets holding the section data.
+    pub fn file_range(&self) -> Range<u32> {
+        self.raw_address..self.raw_address.saturating_add(self.raw_size)
+    }
+}
+
+impl From<&IMAGE_SECTION_HEADER> for SectionInfo {
+    fn from(header: &IMAGE_SECTION_HEADER) -> Self {
+        let name_length = header
+            .Name
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(header.Name.len());
+        SectionInfo {
+            name: String::from_utf8_lossy(&header.Name[..name_length]).into_owned(),
+            virtual_address: header.VirtualAddress,
+            virtual_size: unsafe { header.Misc.VirtualSize },
+            raw_address: header.PointerToRawData,
+            raw_size: header.SizeOfRawData,
+            characteristics: header.Characteristics,
+        }
+    }
 }
 
 /// Retrieves the NT Header for a given process.
@@ -109,16 +1285,14 @@ pub unsafe fn nt_headers(
     image_base: *const c_void,
 ) -> Result<ImageNtHeaders> {
     ensure!(
-        matches!(process, MemoryHandle::Process(_)),
-        "a process handle must be provided"
-    );
-    let dos_header: IMAGE_DOS_HEADER = unsafe { memory::copy(process, image_base as *const _)? };
-    ensure!(
-        dos_header.e_magic == IMAGE_DOS_SIGNATURE,
-        "invalid DOS signature"
+        matches!(
+            process,
+            MemoryHandle::Process(_) | MemoryHandle::Dump(_) | MemoryHandle::File(_)
+        ),
+        "process memory or an image must be provided"
     );
     let nt_headers = {
-        let p_nt_headers = image_base.offset(dos_header.e_lfanew as isize);
+        let p_nt_headers = p_nt_headers(process, image_base)?;
         let nt_common: ImageNtHeadersCommon = memory::copy(process, p_nt_headers as *const _)?;
         match nt_common.file_header.Machine {
             IMAGE_FILE_MACHINE_I386 => {
@@ -138,3 +1312,227 @@ pub unsafe fn nt_headers(
         .then_some(nt_headers)
         .ok_or(anyhow!("invalid NT signature"))
 }
+
+/// Retrieves the section table that follows the NT headers of an image.
+///
+/// # Safety
+///
+/// The provided image base pointer must be valid, and `nt_headers` must have
+/// been read from that same image through [`nt_headers`].
+pub unsafe fn sections(
+    memory: &MemoryHandle,
+    image_base: *const c_void,
+    nt_headers: &ImageNtHeaders,
+) -> Result<Vec<SectionInfo>> {
+    let file_header = nt_headers.file_header();
+    let p_sections = p_nt_headers(memory, image_base)?
+        .add(mem::size_of::<ImageNtHeadersCommon>() + file_header.SizeOfOptionalHeader as usize);
+    trace!(
+        "Reading {} section headers at {:?}",
+        file_header.NumberOfSections,
+        p_sections
+    );
+    let headers: Vec<IMAGE_SECTION_HEADER> = memory::copy_array(
+        memory,
+        p_sections as *const _,
+        file_header.NumberOfSections as usize,
+    )?;
+    Ok(headers.iter().map(SectionInfo::from).collect())
+}
+
+unsafe fn p_nt_headers(memory: &MemoryHandle, image_base: *const c_void) -> Result<*const c_void> {
+    let dos_header: IMAGE_DOS_HEADER = memory::copy(memory, image_base as *const _)?;
+    ensure!(
+        dos_header.e_magic == IMAGE_DOS_SIGNATURE,
+        "invalid DOS signature"
+    );
+    Ok(image_base.offset(dos_header.e_lfanew as isize))
+}
+
+/// Resolves the RVA of a function exported by name from an image, returning
+/// `None` if the image does not export it.
+///
+/// Forwarded exports are also reported as `None`, since their RVA points to
+/// the forwarder string rather than to code within the image.
+///
+/// # Safety
+///
+/// The provided image base pointer must be valid, and `nt_headers` must have
+/// been read from that same image through [`nt_headers`].
+pub unsafe fn export_rva(
+    memory: &MemoryHandle,
+    image_base: *const c_void,
+    nt_headers: &ImageNtHeaders,
+    name: &str,
+) -> Result<Option<u32>> {
+    let directory = nt_headers.data_directory(IMAGE_DIRECTORY_ENTRY_EXPORT);
+    if directory.VirtualAddress == 0 || directory.Size == 0 {
+        trace!("Image has no export directory");
+        return Ok(None);
+    }
+    let exports: IMAGE_EXPORT_DIRECTORY = memory::copy(
+        memory,
+        image_base.add(directory.VirtualAddress as usize) as *const _,
+    )?;
+    let names: Vec<u32> = memory::copy_array(
+        memory,
+        image_base.add(exports.AddressOfNames as usize) as *const _,
+        exports.NumberOfNames as usize,
+    )?;
+    // Export names are sorted in ascending order, which allows a binary search.
+    // Candidates are read up to their terminator, and at most one byte past the
+    // length of the name, which is enough to compare them: reads never go past
+    // the end of short names, where the image may not be readable.
+    let mut range = 0..names.len();
+    let index = loop {
+        if range.is_empty() {
+            return Ok(None);
+        }
+        let middle = range.start + range.len() / 2;
+        let p_candidate = image_base.add(names[middle] as usize) as *const u8;
+        let ordering = match memory::read_cstr(memory, p_candidate, name.len() + 1) {
+            Ok(candidate) => candidate.as_str().cmp(name),
+            // Longer than the name, which the partial read is enough to order
+            Err(error) => match error.downcast_ref::<StringTruncated>() {
+                Some(truncated) => truncated.partial.as_str().cmp(name),
+                None => return Err(error),
+            },
+        };
+        match ordering {
+            Ordering::Less => range.start = middle + 1,
+            Ordering::Greater => range.end = middle,
+            Ordering::Equal => break middle,
+        }
+    };
+    let ordinal: u16 = memory::copy(
+        memory,
+        image_base.add(exports.AddressOfNameOrdinals as usize + index * mem::size_of::<u16>())
+            as *const _,
+    )?;
+    ensure!(
+        (ordinal as u32) < exports.NumberOfFunctions,
+        "export ordinal {} out of range",
+        ordinal
+    );
+    let rva: u32 = memory::copy(
+        memory,
+        image_base
+            .add(exports.AddressOfFunctions as usize + ordinal as usize * mem::size_of::<u32>())
+            as *const _,
+    )?;
+    let directory_range =
+        directory.VirtualAddress..directory.VirtualAddress.saturating_add(directory.Size);
+    if directory_range.contains(&rva) {
+        trace!("Export {} is forwarded", name);
+        return Ok(None);
+    }
+    trace!("Export {} at RVA {:#x}", name, rva);
+    Ok(Some(rva))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_names_are_case_insensitive() {
+        assert_eq!(
+            version_by_image_name("winmine.exe"),
+            Some(Version::WindowsXP)
+        );
+        assert_eq!(
+            version_by_image_name("MINESWEEPER.EXE"),
+            Some(Version::Windows7)
+        );
+        assert_eq!(
+            version_by_image_name(win10::IMAGE_NAME),
+            Some(Version::Windows10)
+        );
+    }
+
+    #[test]
+    fn unknown_image_names() {
+        assert_eq!(version_by_image_name("notepad.exe"), None);
+        // Only whole names match
+        assert_eq!(version_by_image_name("WINMINE"), None);
+        assert_eq!(version_by_image_name("Minesweeper.exe.bak"), None);
+    }
+
+    #[test]
+    fn custom_image_names() {
+        crate::register_version("Sweeper.exe", Box::new(|_| bail!("not a game")));
+        assert_eq!(
+            version_by_image_name("sweeper.EXE"),
+            Some(Version::Custom("Sweeper.exe"))
+        );
+    }
+
+    #[test]
+    fn window_classes_need_the_image() {
+        // Both built-in versions register the same class, so it cannot tell
+        // them apart on its own
+        assert_eq!(
+            window_class(Version::WindowsXP),
+            window_class(Version::Windows7)
+        );
+        assert_eq!(window_class(Version::Windows10), None);
+    }
+
+    #[test]
+    fn export_lookup() {
+        // Export directory, past the headers, followed by the functions, names,
+        // ordinals and the strings of the names, the last one ending the image
+        let mut image = vec![0u8; 0x88];
+        let exports = IMAGE_EXPORT_DIRECTORY {
+            NumberOfFunctions: 3,
+            NumberOfNames: 3,
+            AddressOfFunctions: 0x40,
+            AddressOfNames: 0x50,
+            AddressOfNameOrdinals: 0x60,
+            ..unsafe { mem::zeroed() }
+        };
+        unsafe { std::ptr::write_unaligned(image.as_mut_ptr().add(8) as *mut _, exports) };
+        let put = |image: &mut Vec<u8>, offset: usize, bytes: &[u8]| {
+            image[offset..offset + bytes.len()].copy_from_slice(bytes)
+        };
+        for (i, rva) in [0x1000u32, 0x2000, 0x3000].into_iter().enumerate() {
+            put(&mut image, 0x40 + i * 4, &rva.to_le_bytes());
+        }
+        for (i, rva) in [0x70u32, 0x78, 0x7d].into_iter().enumerate() {
+            put(&mut image, 0x50 + i * 4, &rva.to_le_bytes());
+        }
+        for (i, ordinal) in [2u16, 0, 1].into_iter().enumerate() {
+            put(&mut image, 0x60 + i * 2, &ordinal.to_le_bytes());
+        }
+        put(&mut image, 0x70, b"Alpha\0");
+        put(&mut image, 0x78, b"Beta\0");
+        put(&mut image, 0x7d, b"BetaLonger\0");
+
+        let mut headers: IMAGE_NT_HEADERS32 = unsafe { mem::zeroed() };
+        headers.OptionalHeader.DataDirectory[IMAGE_DIRECTORY_ENTRY_EXPORT.0 as usize] =
+            IMAGE_DATA_DIRECTORY {
+                VirtualAddress: 8,
+                Size: image.len() as u32 - 8,
+            };
+        let headers = ImageNtHeaders::X86(headers);
+        let memory = MemoryHandle::Own;
+        let lookup = |name| unsafe {
+            export_rva(&memory, image.as_ptr() as *const c_void, &headers, name).unwrap()
+        };
+        assert_eq!(lookup("Alpha"), Some(0x3000));
+        assert_eq!(lookup("Beta"), Some(0x1000));
+        assert_eq!(lookup("BetaLonger"), Some(0x2000));
+        // Names sharing a prefix with an export
+        assert_eq!(lookup("Bet"), None);
+        assert_eq!(lookup("BetaLong"), None);
+        assert_eq!(lookup("BetaLongerStill"), None);
+        assert_eq!(lookup("Zeta"), None);
+    }
+
+    #[test]
+    fn versions_by_target_os() {
+        assert_eq!(version_by_target_os(5), Version::WindowsXP);
+        assert_eq!(version_by_target_os(6), Version::Windows7);
+        assert_eq!(version_by_target_os(10), Version::Windows7);
+    }
+}
@@ -0,0 +1,74 @@
+//! Sessions keeping a game process open across reads of its board.
+
+use crate::process::ProcessContext;
+use crate::versions::{windows_7 as win7, windows_xp as winxp};
+use crate::{Board, Version};
+
+use anyhow::{Context, Result};
+use log::debug;
+use std::ffi::c_void;
+
+/// Game structures located when opening a session, for each version.
+enum Located {
+    /// Relocation of the fixed addresses of the game.
+    WindowsXP(winxp::Relocation),
+    /// Address of `G`, the global pointer to the game.
+    Windows7(*const c_void),
+    /// Other versions are read from scratch every time.
+    Other,
+}
+
+/// Game process opened once, along with the location of its structures, so
+/// that its board can be read repeatedly without searching for them again.
+/// Only the game structures are followed on every [`Session::refresh`], which
+/// keeps reads cheap and picks up new games of different dimensions.
+pub struct Session {
+    context: ProcessContext,
+    located: Located,
+}
+
+impl Session {
+    /// Detects a running game, see [`ProcessContext::detect`], and locates
+    /// its structures.
+    pub fn open() -> Result<Session> {
+        Self::from_context(ProcessContext::detect()?)
+    }
+
+    /// Locates the structures of the game run by an already opened process.
+    pub fn from_context(context: ProcessContext) -> Result<Session> {
+        let located = match context.version {
+            Version::WindowsXP => Located::WindowsXP(winxp::Relocation::new(&context)?),
+            Version::Windows7 => Located::Windows7(win7::locate(&context)?),
+            Version::Windows10 | Version::Custom(_) => Located::Other,
+        };
+        Ok(Session { context, located })
+    }
+
+    /// Returns the game process the session reads from.
+    pub fn context(&self) -> &ProcessContext {
+        &self.context
+    }
+
+    /// Returns whether the game process is still running.
+    pub fn is_running(&self) -> bool {
+        self.context.is_running()
+    }
+
+    /// Reads the current board of the game. If the structures located when
+    /// opening the session turn out to be stale, they are located again.
+    pub fn refresh(&mut self) -> Result<Board> {
+        match &mut self.located {
+            Located::WindowsXP(relocation) => winxp::read_board(&self.context, relocation),
+            Located::Windows7(p_g) => match win7::read_board(&self.context, *p_g) {
+                Ok(board) => Ok(board),
+                Err(error) => {
+                    debug!("Locating the game again, as reading it failed: {:#}", error);
+                    *p_g = win7::locate(&self.context)?;
+                    win7::read_board(&self.context, *p_g)
+                }
+            },
+            Located::Other => return super::board(&self.context),
+        }
+        .context("unable to retrieve game board")
+    }
+}
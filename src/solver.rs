@@ -0,0 +1,596 @@
+//! Deductive solver suggesting the next moves of a game in progress.
+
+use crate::{Board, BoardState, Cell};
+
+use anyhow::Result;
+
+use std::collections::{HashMap, HashSet};
+
+/// Row and column of a cell on the board.
+pub type Position = (usize, usize);
+
+/// Hidden cells whose content follows from the numbers on the board, as
+/// returned by [`solve`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Deductions {
+    /// Coordinates of the cells that can be safely opened.
+    pub safe: HashSet<Position>,
+    /// Coordinates of the cells that are certain to hold a mine.
+    pub mines: HashSet<Position>,
+}
+
+impl Deductions {
+    /// Returns `true` if nothing could be deduced from the board.
+    pub fn is_empty(&self) -> bool {
+        self.safe.is_empty() && self.mines.is_empty()
+    }
+
+    /// Returns the coordinates of the mines and of the safe cells, in that
+    /// order, each sorted by row and then by column.
+    pub fn to_sorted(&self) -> (Vec<Position>, Vec<Position>) {
+        let sorted = |cells: &HashSet<Position>| {
+            let mut cells: Vec<_> = cells.iter().copied().collect();
+            cells.sort_unstable();
+            cells
+        };
+        (sorted(&self.mines), sorted(&self.safe))
+    }
+}
+
+/// Cells deduced from a [`BoardState`], as returned by [`analyze`]. Both lists
+/// are sorted by row and then by column.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Solution {
+    /// Coordinates of the cells that can be safely opened.
+    pub safe: Vec<Position>,
+    /// Coordinates of the cells that are certain to hold a mine.
+    pub mines: Vec<Position>,
+}
+
+/// Same as [`solve`], for a board state, which may have been exported
+/// earlier or built by hand. Fails if the rows of the state differ in length.
+/// Boards without any opened number, such as fresh ones, have no deductions.
+pub fn analyze(state: &BoardState) -> Result<Solution> {
+    let board = Board::from_cells(state.cells.clone(), state.mines)?;
+    let (mines, safe) = solve(&board).to_sorted();
+    Ok(Solution { safe, mines })
+}
+
+/// Deduces which hidden cells are safe and which hold a mine, only relying on
+/// the numbers of the opened cells around them. Flags are trusted to be placed
+/// on mines, while question marks are treated as hidden cells.
+///
+/// Each number is checked on its own: once it is satisfied by the mines around
+/// it, the rest of its neighbours are safe, and once its remaining mines match
+/// its remaining hidden neighbours, all of them are mines. The process is
+/// repeated until no further cell can be deduced.
+pub fn solve(board: &Board) -> Deductions {
+    let mut deductions = Deductions::default();
+    let numbers: Vec<_> = board
+        .iter()
+        .filter_map(|(r, c, cell)| match cell {
+            Cell::Number(n) => Some((r, c, *n as usize)),
+            _ => None,
+        })
+        .collect();
+    let mut progress = true;
+    while progress {
+        progress = false;
+        for &(row, column, n) in &numbers {
+            let (mut mines, mut unknown) = (0, Vec::new());
+            for (r, c) in board.neighbors(row, column) {
+                match board.cell(r, c) {
+                    Some(Cell::Flag | Cell::Mine) => mines += 1,
+                    Some(Cell::Hidden | Cell::Question) if deductions.mines.contains(&(r, c)) => {
+                        mines += 1
+                    }
+                    Some(Cell::Hidden | Cell::Question) if !deductions.safe.contains(&(r, c)) => {
+                        unknown.push((r, c))
+                    }
+                    _ => {}
+                }
+            }
+            if unknown.is_empty() || mines > n {
+                continue;
+            }
+            let deduced = if mines == n {
+                &mut deductions.safe
+            } else if n - mines == unknown.len() {
+                &mut deductions.mines
+            } else {
+                continue;
+            };
+            deduced.extend(unknown);
+            progress = true;
+        }
+    }
+    deductions
+}
+
+/// Default limit of cells of a constraint group whose mine placements are
+/// enumerated by [`probabilities`], keeping the worst case at about a million
+/// placements.
+pub const DEFAULT_MAX_GROUP_SIZE: usize = 20;
+
+/// Mines left around an opened number, among the hidden cells around it whose
+/// content is still unknown.
+struct Constraint {
+    cells: Vec<Position>,
+    mines: usize,
+}
+
+/// Placements of the mines of a constraint group, counted by the number of
+/// mines they use.
+struct GroupCounts {
+    cells: Vec<Position>,
+    /// Number of placements using each number of mines.
+    total: Vec<f64>,
+    /// Same as `total`, only counting the placements with a mine on each cell.
+    mined: Vec<Vec<f64>>,
+}
+
+/// Estimates the probability of holding a mine of every hidden cell, flags
+/// excluded, for guessing when [`solve`] finds no certain move. Deduced cells
+/// get a probability of exactly `0` or `1`.
+///
+/// The rest of the cells next to opened numbers are split into groups sharing
+/// constraints. The mine placements of each group that satisfy its numbers are
+/// enumerated, and weighted by the number of ways the remaining mines of the
+/// board fit in the cells away from any number, so that the total number of
+/// mines is taken into account. Cells away from numbers share the density of
+/// the mines left for them.
+///
+/// Groups of more than `max_group_size` cells are not enumerated, as their
+/// placements grow exponentially: their cells are estimated by averaging the
+/// density of mines of the numbers around them instead, and the mines expected
+/// in them are set aside from the total. See [`DEFAULT_MAX_GROUP_SIZE`].
+///
+/// Fails if the rows of the state differ in length. Boards whose numbers
+/// cannot be satisfied, which may be misread, get each group estimated on its
+/// own, ignoring the total number of mines.
+pub fn probabilities(state: &BoardState, max_group_size: usize) -> Result<HashMap<Position, f64>> {
+    let board = Board::from_cells(state.cells.clone(), state.mines)?;
+    let deductions = solve(&board);
+    let mut probabilities = HashMap::new();
+    let mut known_mines = 0;
+    let mut unknown = HashSet::new();
+    for (r, c, cell) in board.iter() {
+        match cell {
+            Cell::Flag | Cell::Mine => known_mines += 1,
+            Cell::Hidden | Cell::Question if deductions.mines.contains(&(r, c)) => {
+                known_mines += 1;
+                probabilities.insert((r, c), 1.0);
+            }
+            Cell::Hidden | Cell::Question if deductions.safe.contains(&(r, c)) => {
+                probabilities.insert((r, c), 0.0);
+            }
+            Cell::Hidden | Cell::Question => {
+                unknown.insert((r, c));
+            }
+            _ => {}
+        }
+    }
+
+    let constraints = constraints(&board, &deductions, &unknown);
+    let mut remaining = (state.mines as usize).saturating_sub(known_mines);
+    let mut groups = Vec::new();
+    let mut constrained = HashSet::new();
+    for group in groups_of(&constraints) {
+        constrained.extend(group.iter().copied());
+        let group_constraints: Vec<_> = constraints
+            .iter()
+            .filter(|constraint| group.contains(&constraint.cells[0]))
+            .collect();
+        let counts = (group.len() <= max_group_size)
+            .then(|| enumerate(&group, &group_constraints))
+            .filter(|counts| counts.total.iter().sum::<f64>() > 0.0);
+        match counts {
+            Some(counts) => groups.push(counts),
+            None => {
+                let estimates = average_density(&group, &group_constraints);
+                let expected: f64 = estimates.iter().map(|(_, p)| p).sum();
+                remaining = remaining.saturating_sub(expected.round() as usize);
+                probabilities.extend(estimates);
+            }
+        }
+    }
+    let unconstrained: Vec<_> = unknown
+        .iter()
+        .filter(|cell| !constrained.contains(*cell))
+        .copied()
+        .collect();
+
+    // Ways to place the mines left once `mines` are placed in the groups
+    let free = unconstrained.len();
+    let ways = |mines: usize| match remaining.checked_sub(mines) {
+        Some(left) if left <= free => binomial(free, left),
+        _ => 0.0,
+    };
+    let all = groups
+        .iter()
+        .fold(vec![1.0], |all, group| convolve(&all, &group.total));
+    let weight: f64 = all.iter().enumerate().map(|(k, n)| n * ways(k)).sum();
+    if weight > 0.0 {
+        for (g, group) in groups.iter().enumerate() {
+            let others = groups
+                .iter()
+                .enumerate()
+                .filter(|&(other, _)| other != g)
+                .fold(vec![1.0], |all, (_, group)| convolve(&all, &group.total));
+            // Weight of the placements of the group using each number of mines
+            let weights: Vec<f64> = (0..group.total.len())
+                .map(|k| {
+                    others
+                        .iter()
+                        .enumerate()
+                        .map(|(o, n)| n * ways(k + o))
+                        .sum()
+                })
+                .collect();
+            for (cell, mined) in group.cells.iter().zip(&group.mined) {
+                let p: f64 = mined.iter().zip(&weights).map(|(n, w)| n * w).sum();
+                probabilities.insert(*cell, p / weight);
+            }
+        }
+        if free > 0 {
+            let expected: f64 = all
+                .iter()
+                .enumerate()
+                .map(|(k, n)| n * ways(k) * remaining.saturating_sub(k) as f64)
+                .sum();
+            let p = expected / weight / free as f64;
+            probabilities.extend(unconstrained.iter().map(|&cell| (cell, p)));
+        }
+    } else {
+        for group in &groups {
+            let total: f64 = group.total.iter().sum();
+            for (cell, mined) in group.cells.iter().zip(&group.mined) {
+                probabilities.insert(*cell, mined.iter().sum::<f64>() / total);
+            }
+        }
+        if free > 0 {
+            let p = (remaining as f64 / free as f64).min(1.0);
+            probabilities.extend(unconstrained.iter().map(|&cell| (cell, p)));
+        }
+    }
+    Ok(probabilities)
+}
+
+/// Returns the constraints of the opened numbers with undecided hidden cells
+/// around them. Numbers contradicted by the mines around them are skipped.
+fn constraints(
+    board: &Board,
+    deductions: &Deductions,
+    unknown: &HashSet<Position>,
+) -> Vec<Constraint> {
+    board
+        .iter()
+        .filter_map(|(row, column, cell)| {
+            let Cell::Number(n) = cell else {
+                return None;
+            };
+            let (mut mines, mut cells) = (0, Vec::new());
+            for (r, c) in board.neighbors(row, column) {
+                match board.cell(r, c) {
+                    Some(Cell::Flag | Cell::Mine) => mines += 1,
+                    _ if deductions.mines.contains(&(r, c)) => mines += 1,
+                    _ if unknown.contains(&(r, c)) => cells.push((r, c)),
+                    _ => {}
+                }
+            }
+            let mines = (*n as usize).checked_sub(mines)?;
+            (!cells.is_empty() && mines <= cells.len()).then_some(Constraint { cells, mines })
+        })
+        .collect()
+}
+
+/// Splits the cells of the constraints into groups, two cells being in the
+/// same group if they are linked by a chain of shared constraints.
+fn groups_of(constraints: &[Constraint]) -> Vec<Vec<Position>> {
+    let mut groups: Vec<Vec<Position>> = Vec::new();
+    for constraint in constraints {
+        let (linked, mut rest): (Vec<_>, Vec<_>) = groups
+            .into_iter()
+            .partition(|group| constraint.cells.iter().any(|cell| group.contains(cell)));
+        let mut merged: Vec<Position> = linked.into_iter().flatten().collect();
+        for cell in &constraint.cells {
+            if !merged.contains(cell) {
+                merged.push(*cell);
+            }
+        }
+        rest.push(merged);
+        groups = rest;
+    }
+    groups
+}
+
+/// Counts the mine placements over the cells of a group satisfying all of its
+/// constraints, backtracking as soon as one of them cannot be satisfied.
+fn enumerate(cells: &[Position], constraints: &[&Constraint]) -> GroupCounts {
+    // Constraints of each cell, and the mines placed and cells left for each
+    let cell_constraints: Vec<Vec<usize>> = cells
+        .iter()
+        .map(|cell| {
+            (0..constraints.len())
+                .filter(|&i| constraints[i].cells.contains(cell))
+                .collect()
+        })
+        .collect();
+    let mut placed = vec![0; constraints.len()];
+    let mut left: Vec<usize> = constraints.iter().map(|c| c.cells.len()).collect();
+    let mut mined = vec![false; cells.len()];
+    let mut counts = GroupCounts {
+        cells: cells.to_vec(),
+        total: vec![0.0; cells.len() + 1],
+        mined: vec![vec![0.0; cells.len() + 1]; cells.len()],
+    };
+
+    fn place(
+        index: usize,
+        constraints: &[&Constraint],
+        cell_constraints: &[Vec<usize>],
+        placed: &mut [usize],
+        left: &mut [usize],
+        mined: &mut [bool],
+        counts: &mut GroupCounts,
+    ) {
+        if index == mined.len() {
+            let k = mined.iter().filter(|&&mine| mine).count();
+            counts.total[k] += 1.0;
+            for (cell, _) in mined.iter().enumerate().filter(|(_, &mine)| mine) {
+                counts.mined[cell][k] += 1.0;
+            }
+            return;
+        }
+        for mine in [false, true] {
+            let related = &cell_constraints[index];
+            for &i in related {
+                left[i] -= 1;
+                placed[i] += mine as usize;
+            }
+            let feasible = related.iter().all(|&i| {
+                placed[i] <= constraints[i].mines && placed[i] + left[i] >= constraints[i].mines
+            });
+            if feasible {
+                mined[index] = mine;
+                place(
+                    index + 1,
+                    constraints,
+                    cell_constraints,
+                    placed,
+                    left,
+                    mined,
+                    counts,
+                );
+                mined[index] = false;
+            }
+            for &i in related {
+                left[i] += 1;
+                placed[i] -= mine as usize;
+            }
+        }
+    }
+
+    place(
+        0,
+        constraints,
+        &cell_constraints,
+        &mut placed,
+        &mut left,
+        &mut mined,
+        &mut counts,
+    );
+    counts
+}
+
+/// Estimates the probability of each cell of a group as the average density
+/// of mines left around the numbers constraining it.
+fn average_density(cells: &[Position], constraints: &[&Constraint]) -> Vec<(Position, f64)> {
+    cells
+        .iter()
+        .map(|cell| {
+            let densities: Vec<f64> = constraints
+                .iter()
+                .filter(|constraint| constraint.cells.contains(cell))
+                .map(|constraint| constraint.mines as f64 / constraint.cells.len() as f64)
+                .collect();
+            (
+                *cell,
+                densities.iter().sum::<f64>() / densities.len() as f64,
+            )
+        })
+        .collect()
+}
+
+/// Convolves two counts of placements by number of mines, giving the counts
+/// of their combined placements.
+fn convolve(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let mut result = vec![0.0; a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            result[i + j] += x * y;
+        }
+    }
+    result
+}
+
+/// Number of ways to choose `k` elements out of `n`, as a float since it
+/// quickly grows past any integer type on large boards.
+fn binomial(n: usize, k: usize) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+    let k = k.min(n - k);
+    (1..=k).fold(1.0, |ways, i| ways * (n - k + i) as f64 / i as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(rows: &[&str], mines: u32) -> Board {
+        Board::from_glyphs(rows, mines).unwrap()
+    }
+
+    #[test]
+    fn solve_opens_around_satisfied_numbers() {
+        let deductions = solve(&board(&["1F.", "..."], 2));
+        assert_eq!(deductions.to_sorted(), (vec![], vec![(1, 0), (1, 1)]));
+    }
+
+    #[test]
+    fn solve_chains_deductions() {
+        // The mine next to the first number satisfies the second one
+        let deductions = solve(&board(&["1.1..", "   .."], 2));
+        assert_eq!(deductions.to_sorted(), (vec![(0, 1)], vec![(0, 3), (1, 3)]));
+    }
+
+    #[test]
+    fn solve_treats_question_marks_as_hidden() {
+        let deductions = solve(&board(&["1?", "  "], 1));
+        assert_eq!(deductions.to_sorted(), (vec![(0, 1)], vec![]));
+    }
+
+    #[test]
+    fn solve_skips_contradicted_numbers() {
+        let deductions = solve(&board(&["1F", "F."], 2));
+        assert!(deductions.is_empty());
+    }
+
+    #[test]
+    fn solve_fresh_board() {
+        assert!(solve(&Board::new(9, 9, 10)).is_empty());
+    }
+
+    #[test]
+    fn analyze_sorts_deductions() {
+        let state = board(&["1.1..", "   .."], 2).to_state();
+        let solution = analyze(&state).unwrap();
+        assert_eq!(solution.mines, [(0, 1)]);
+        assert_eq!(solution.safe, [(0, 3), (1, 3)]);
+    }
+
+    #[test]
+    fn analyze_fresh_board() {
+        let state = Board::new(9, 9, 10).to_state();
+        assert_eq!(analyze(&state).unwrap(), Solution::default());
+    }
+
+    #[test]
+    fn analyze_rejects_ragged_rows() {
+        let mut state = board(&["1.1..", "   .."], 2).to_state();
+        state.cells[1].pop();
+        assert!(analyze(&state).is_err());
+    }
+
+    fn assert_probabilities(estimates: &HashMap<Position, f64>, expected: &[(Position, f64)]) {
+        assert_eq!(estimates.len(), expected.len());
+        for (cell, p) in expected {
+            assert!(
+                (estimates[cell] - p).abs() < 1e-9,
+                "probability of {cell:?} is {}, expected {p}",
+                estimates[cell]
+            );
+        }
+    }
+
+    #[test]
+    fn probabilities_of_one_two_one() {
+        let state = board(&["...", "121"], 2).to_state();
+        let estimates = probabilities(&state, DEFAULT_MAX_GROUP_SIZE).unwrap();
+        assert_probabilities(&estimates, &[((0, 0), 1.0), ((0, 1), 0.0), ((0, 2), 1.0)]);
+    }
+
+    #[test]
+    fn probabilities_of_deduced_cells() {
+        let state = board(&["1.1..", "   .."], 2).to_state();
+        let estimates = probabilities(&state, DEFAULT_MAX_GROUP_SIZE).unwrap();
+        assert_eq!(estimates[&(0, 1)], 1.0);
+        assert_eq!(estimates[&(0, 3)], 0.0);
+        assert_eq!(estimates[&(1, 3)], 0.0);
+        // The mine left is in either of the last two cells
+        assert_eq!(estimates[&(0, 4)], 0.5);
+        assert_eq!(estimates[&(1, 4)], 0.5);
+    }
+
+    #[test]
+    fn probabilities_weight_placements_by_total_mines() {
+        // Either the middle cell holds a mine, or both corners do, leaving
+        // fewer mines for the six cells away from the numbers
+        let rows = ["...", "1 1", "   ", "...", "..."];
+        let state = board(&rows, 2).to_state();
+        let estimates = probabilities(&state, DEFAULT_MAX_GROUP_SIZE).unwrap();
+        let free = [(3, 0), (3, 1), (3, 2), (4, 0), (4, 1), (4, 2)];
+        let mut expected = vec![
+            ((0, 0), 1.0 / 7.0),
+            ((0, 1), 6.0 / 7.0),
+            ((0, 2), 1.0 / 7.0),
+        ];
+        expected.extend(free.map(|cell| (cell, 1.0 / 7.0)));
+        assert_probabilities(&estimates, &expected);
+
+        let state = board(&rows, 3).to_state();
+        let estimates = probabilities(&state, DEFAULT_MAX_GROUP_SIZE).unwrap();
+        let mut expected = vec![
+            ((0, 0), 2.0 / 7.0),
+            ((0, 1), 5.0 / 7.0),
+            ((0, 2), 2.0 / 7.0),
+        ];
+        expected.extend(free.map(|cell| (cell, 2.0 / 7.0)));
+        assert_probabilities(&estimates, &expected);
+    }
+
+    #[test]
+    fn probabilities_of_fresh_board() {
+        let state = Board::new(3, 3, 2).to_state();
+        let estimates = probabilities(&state, DEFAULT_MAX_GROUP_SIZE).unwrap();
+        let expected: Vec<_> = state
+            .cells
+            .iter()
+            .enumerate()
+            .flat_map(|(r, row)| (0..row.len()).map(move |c| ((r, c), 2.0 / 9.0)))
+            .collect();
+        assert_probabilities(&estimates, &expected);
+    }
+
+    #[test]
+    fn probabilities_exclude_flags() {
+        let state = board(&["F.", ".."], 2).to_state();
+        let estimates = probabilities(&state, DEFAULT_MAX_GROUP_SIZE).unwrap();
+        assert_probabilities(
+            &estimates,
+            &[
+                ((0, 1), 1.0 / 3.0),
+                ((1, 0), 1.0 / 3.0),
+                ((1, 1), 1.0 / 3.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn probabilities_of_large_groups_use_average_density() {
+        let state = board(&["...", "121"], 2).to_state();
+        let estimates = probabilities(&state, 2).unwrap();
+        let expected = [
+            ((0, 0), 7.0 / 12.0),
+            ((0, 1), 5.0 / 9.0),
+            ((0, 2), 7.0 / 12.0),
+        ];
+        assert_probabilities(&estimates, &expected);
+    }
+
+    #[test]
+    fn probabilities_ignore_unsatisfiable_total() {
+        // The numbers need two mines, but the board claims a single one
+        let state = board(&["...", "121"], 1).to_state();
+        let estimates = probabilities(&state, DEFAULT_MAX_GROUP_SIZE).unwrap();
+        assert_probabilities(&estimates, &[((0, 0), 1.0), ((0, 1), 0.0), ((0, 2), 1.0)]);
+    }
+
+    #[test]
+    fn probabilities_reject_ragged_rows() {
+        let mut state = board(&["...", "121"], 2).to_state();
+        state.cells[0].pop();
+        assert!(probabilities(&state, DEFAULT_MAX_GROUP_SIZE).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+//! Colors used to render the cells of boards.
+
+use crate::Cell;
+
+use colored::{Color, ColoredString, Colorize};
+use std::sync::{PoisonError, RwLock};
+
+/// Style applied to the glyph of a cell. Styles are ignored when colors are
+/// off, see [`set_color_mode`](crate::set_color_mode).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CellStyle {
+    /// Color of the glyph, or the default one of the terminal.
+    pub foreground: Option<Color>,
+    /// Color behind the glyph, or the default one of the terminal.
+    pub background: Option<Color>,
+    /// Whether the glyph is written in bold.
+    pub bold: bool,
+}
+
+impl CellStyle {
+    /// Style using the default colors of the terminal.
+    pub const PLAIN: CellStyle = CellStyle {
+        foreground: None,
+        background: None,
+        bold: false,
+    };
+
+    /// Style of a glyph of the given color.
+    pub const fn foreground(color: Color) -> CellStyle {
+        CellStyle {
+            foreground: Some(color),
+            ..Self::PLAIN
+        }
+    }
+
+    /// Style of a glyph over the given color.
+    pub const fn background(color: Color) -> CellStyle {
+        CellStyle {
+            background: Some(color),
+            ..Self::PLAIN
+        }
+    }
+
+    /// Applies the style to a glyph.
+    pub fn apply(&self, glyph: &str) -> ColoredString {
+        let mut styled = ColoredString::from(glyph);
+        if let Some(color) = self.foreground {
+            styled = styled.color(color);
+        }
+        if let Some(color) = self.background {
+            styled = styled.on_color(color);
+        }
+        if self.bold {
+            styled = styled.bold();
+        }
+        styled
+    }
+}
+
+/// Styles of every kind of cell, used by all the versions of the game alike.
+/// The [`Default`] theme mimics the colors of the game itself, and others can
+/// be selected through [`set_theme`], for instance for colorblind-friendly
+/// schemes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Style of [`Cell::Hidden`].
+    pub hidden: CellStyle,
+    /// Style of [`Cell::Empty`].
+    pub empty: CellStyle,
+    /// Styles of [`Cell::Number`], from 1 to 8.
+    pub numbers: [CellStyle; 8],
+    /// Style of [`Cell::Flag`].
+    pub flag: CellStyle,
+    /// Style of [`Cell::Question`].
+    pub question: CellStyle,
+    /// Style of [`Cell::Mine`].
+    pub mine: CellStyle,
+    /// Style of [`Cell::Unknown`].
+    pub unknown: CellStyle,
+}
+
+impl Theme {
+    /// Theme mimicking the colors of the game itself.
+    pub const CLASSIC: Theme = Theme {
+        hidden: CellStyle::PLAIN,
+        empty: CellStyle::PLAIN,
+        numbers: [
+            CellStyle::foreground(Color::Blue),
+            CellStyle::foreground(Color::Green),
+            CellStyle::foreground(Color::Red),
+            CellStyle::foreground(Color::Magenta),
+            CellStyle::foreground(Color::TrueColor { r: 94, g: 9, b: 28 }),
+            CellStyle::foreground(Color::Cyan),
+            CellStyle::foreground(Color::BrightBlue),
+            CellStyle::foreground(Color::BrightGreen),
+        ],
+        flag: CellStyle::background(Color::Red),
+        question: CellStyle {
+            foreground: Some(Color::Black),
+            background: Some(Color::White),
+            bold: false,
+        },
+        mine: CellStyle::foreground(Color::BrightRed),
+        unknown: CellStyle {
+            foreground: Some(Color::Red),
+            background: None,
+            bold: true,
+        },
+    };
+
+    /// Theme without colors, only setting flags, mines and unrecognized cells
+    /// in bold, for monochrome terminals.
+    pub const MONOCHROME: Theme = Theme {
+        hidden: CellStyle::PLAIN,
+        empty: CellStyle::PLAIN,
+        numbers: [CellStyle::PLAIN; 8],
+        flag: CellStyle {
+            bold: true,
+            ..CellStyle::PLAIN
+        },
+        question: CellStyle::PLAIN,
+        mine: CellStyle {
+            bold: true,
+            ..CellStyle::PLAIN
+        },
+        unknown: CellStyle {
+            bold: true,
+            ..CellStyle::PLAIN
+        },
+    };
+
+    /// Returns the style of the given cell.
+    pub fn style(&self, cell: &Cell) -> CellStyle {
+        match cell {
+            Cell::Hidden => self.hidden,
+            Cell::Empty => self.empty,
+            Cell::Number(n) => (*n as usize)
+                .checked_sub(1)
+                .and_then(|index| self.numbers.get(index))
+                .copied()
+                .unwrap_or(self.unknown),
+            Cell::Flag => self.flag,
+            Cell::Question => self.question,
+            Cell::Mine => self.mine,
+            Cell::Unknown => self.unknown,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::CLASSIC
+    }
+}
+
+/// Theme used to render boards, see [`set_theme`].
+static THEME: RwLock<Theme> = RwLock::new(Theme::CLASSIC);
+
+/// Selects the theme used to render the boards written from now on. Without
+/// calling it, [`Theme::CLASSIC`] is used.
+pub fn set_theme(theme: Theme) {
+    *THEME.write().unwrap_or_else(PoisonError::into_inner) = theme;
+}
+
+/// Returns the theme selected through [`set_theme`].
+pub(crate) fn current() -> Theme {
+    *THEME.read().unwrap_or_else(PoisonError::into_inner)
+}
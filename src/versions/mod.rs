@@ -1,4 +1,5 @@
 //! Versions of minesweeper supported by this crate.
 
+pub mod windows_10;
 pub mod windows_7;
 pub mod windows_xp;
@@ -0,0 +1,19 @@
+//! This modules contains the necessary functions to interface with the
+//! Microsoft Store version of Minesweeper, shipped for Windows 8 and later.
+//!
+//! The game is only detected for now: the layout of its structures has not
+//! been reverse engineered yet, so its board cannot be read.
+
+use crate::process::ProcessContext;
+use crate::{Board, Error};
+
+use anyhow::Result;
+
+/// Image name of the game executable, run from its app package.
+pub const IMAGE_NAME: &str = "MicrosoftMinesweeper.exe";
+
+/// Retrieve the board state from the provided process. Always fails with
+/// [`Error::UnsupportedVersion`], as reading this version is not supported yet.
+pub fn board(context: &ProcessContext) -> Result<Board> {
+    Err(Error::UnsupportedVersion(context.version).into())
+}
@@ -2,39 +2,106 @@
 //! Windows 7 version of Minesweeper.
 
 use crate::memory::{self, MemoryHandle};
-use crate::process::{self, ImageNtHeaders};
-use crate::Board;
+use crate::process::{self, Architecture, ProcessContext};
+use crate::{Board, Cell, Error, GameStatus};
 
-use anyhow::{anyhow, bail, Context, Result};
-use colored::*;
-use lazy_static::lazy_static;
-use log::{debug, trace};
-use std::ffi::c_void;
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use log::{debug, trace, warn};
+use std::{ffi::c_void, mem, ops::Range};
+use windows::Win32::System::Diagnostics::Debug::{IMAGE_SCN_CNT_CODE, IMAGE_SCN_MEM_EXECUTE};
 
-const WIN6_SAFE_GET_SINGLETON: [u8; 14] = [
-    0x48, 0x89, 0x44, 0x24, 0x70, 0x48, 0x85, 0xc0, 0x74, 0x0a, 0x48, 0x8b, 0xc8, 0xe8,
+/// Class name of the main game window. Unlike the window title, it is the
+/// same on every localized release of the game.
+pub const WINDOW_CLASS: &str = "Minesweeper";
+
+/// Instructions following the call to `Game::SafeGetSingleton` in the x64
+/// build of the game. The stack offset of the `mov [rsp + 70h], rax`
+/// instruction and the branch distance of the `je` may change between builds,
+/// so they are wildcards.
+const WIN6_SAFE_GET_SINGLETON: [Option<u8>; 14] = [
+    Some(0x48),
+    Some(0x89),
+    Some(0x44),
+    Some(0x24),
+    None,
+    Some(0x48),
+    Some(0x85),
+    Some(0xc0),
+    Some(0x74),
+    None,
+    Some(0x48),
+    Some(0x8b),
+    Some(0xc8),
+    Some(0xe8),
+];
+/// Instructions following the call to `Game::SafeGetSingleton` in the x86
+/// build of the game: `test al, al; jnz; push 67h; call`, leaving the branch
+/// distance as a wildcard.
+const WIN6_SAFE_GET_SINGLETON_X86: [Option<u8>; 7] = [
+    Some(0x84),
+    Some(0xc0),
+    Some(0x75),
+    None,
+    Some(0x6a),
+    Some(0x67),
+    Some(0xe8),
+];
+/// RIP-relative instructions accessing `G` ahead of the Get Singleton pattern,
+/// given as their opcode bytes followed by any trailing immediate bytes.
+const G_ACCESSES: [(&[u8], &[u8]); 2] = [
+    // cmp qword ptr [rip + disp32], 0
+    (&[0x48, 0x83, 0x3d], &[0x00]),
+    // mov rax, qword ptr [rip + disp32]
+    (&[0x48, 0x8b, 0x05], &[]),
+];
+/// Same as [`G_ACCESSES`] for the x86 build, where `G` is accessed through its
+/// absolute address instead.
+const G_ACCESSES_X86: [(&[u8], &[u8]); 3] = [
+    // cmp dword ptr [addr32], 0
+    (&[0x83, 0x3d], &[0x00]),
+    // mov eax, dword ptr [addr32]
+    (&[0xa1], &[]),
+    // mov ecx, dword ptr [addr32]
+    (&[0x8b, 0x0d], &[]),
+];
+/// Number of bytes before the Get Singleton pattern searched for `G` accesses.
+const G_ACCESS_WINDOW: usize = 64;
+const SOFTWARE_BREAKPOINT: u8 = 0xcc;
+/// Largest number of pattern bytes taken as overwritten by software
+/// breakpoints when a debugger is attached.
+const MAX_BREAKPOINTS: usize = 2;
+/// Largest number of rows or columns of a custom game, used to tell boards
+/// apart from unrelated memory.
+const MAX_BOARD_SIDE: usize = 30;
+
+/// Cells shown for each value of the revealed state array (`ref_visibles`):
+///
+/// - `0` to `8`: opened cell with that number of adjacent mines.
+/// - `9`: cell not opened yet.
+/// - `10`: flagged cell.
+/// - `11`: cell marked with a question mark.
+/// - `12`: opened cell, as shown once the game is over.
+/// - `13` and `14`: unidentified states, shown as unknown.
+///
+/// Any other value is rejected as a bad read. Mines are not part of these
+/// values, they are overlaid from the mine array (`ref_mines`) afterwards.
+const DISP_MINESWEEPER: [Cell; 15] = [
+    Cell::Empty,
+    Cell::Number(1),
+    Cell::Number(2),
+    Cell::Number(3),
+    Cell::Number(4),
+    Cell::Number(5),
+    Cell::Number(6),
+    Cell::Number(7),
+    Cell::Number(8),
+    Cell::Hidden,
+    Cell::Flag,
+    Cell::Question,
+    Cell::Empty,
+    Cell::Unknown,
+    Cell::Unknown,
 ];
-const OFFS_WIN6_TO_G: isize = -21;
-
-lazy_static! {
-    static ref DISP_MINESWEEPER: Vec<ColoredString> = vec![
-        "0".into(),
-        "1".blue(),
-        "2".green(),
-        "3".red(),
-        "4".purple(),
-        "5".truecolor(94, 9, 28),
-        "6".cyan(),
-        "7".bright_blue(),
-        "8".bright_green(),
-        ".".into(),
-        "F".on_red(),
-        "?".black().on_white(),
-        " ".into(),
-        "!".red(),
-        "!".red().bold(),
-    ];
-}
 
 #[repr(C)]
 struct MinesweeperElement {
@@ -46,14 +113,14 @@ struct MinesweeperElement {
     unk3: u32,
 }
 
-#[derive(Clone)]
 #[repr(C)]
-struct PMinesweeperElement(*const MinesweeperElement);
-
-impl Default for PMinesweeperElement {
-    fn default() -> Self {
-        PMinesweeperElement(std::ptr::null())
-    }
+struct MinesweeperElement32 {
+    cb_elements: u32,
+    unk0: u32,
+    unk1: u32,
+    elements: u32,
+    unk2: u32,
+    unk3: u32,
 }
 
 #[repr(C)]
@@ -80,6 +147,30 @@ struct MinesweeperBoard {
     unk13: u32,
 }
 
+#[repr(C)]
+struct MinesweeperBoard32 {
+    serializer: u32,
+    cb_mines: u32,
+    cb_rows: u32,
+    cb_columns: u32,
+    unk0: u32,
+    unk1: u32,
+    unk2: u32,
+    unk3: u32,
+    unk4: u32,
+    unk5: u32,
+    unk6: u32,
+    unk7: u32,
+    unk8: u32,
+    unk9: u32,
+    unk10: u32,
+    unk11: u32,
+    ref_visibles: u32,
+    ref_mines: u32,
+    unk12: u32,
+    unk13: u32,
+}
+
 #[repr(C)]
 struct MinesweeperGame {
     serializer: *mut c_void,
@@ -88,93 +179,448 @@ struct MinesweeperGame {
     p_board: *mut MinesweeperBoard,
 }
 
+#[repr(C)]
+struct MinesweeperGame32 {
+    serializer: u32,
+    p_node_base: u32,
+    p_board_canvas: u32,
+    p_board: u32,
+}
+
+/// Fields of a `MinesweeperElement` in use, read for either architecture.
+struct Element {
+    cb_elements: u32,
+    elements: *const c_void,
+}
+
+/// Fields of a `MinesweeperBoard` in use, read for either architecture.
+struct BoardInfo {
+    cb_mines: u32,
+    cb_rows: u32,
+    cb_columns: u32,
+    ref_visibles: *const c_void,
+    ref_mines: *const c_void,
+}
+
 enum Visibility {
     Revealed,
     Hidden,
 }
 
 /// Retrieve the board state from the provided process.
-pub fn board(a_remote: MemoryHandle) -> Result<Board> {
-    debug!("Accessing Minesweeper's PEB");
-    let peb = process::peb(&a_remote, false).context("unable to access process' PEB")?;
-    trace!("PEB Image Base address: {:#?}", peb.image_base_address);
-    let ntheaders = unsafe { process::nt_headers(&a_remote, peb.image_base_address) }
-        .context("unable to access process' NT header")?;
-    let (image_base, image_size) = match ntheaders {
-        ImageNtHeaders::X64(headers) => (
-            headers.OptionalHeader.ImageBase as *const _,
-            headers.OptionalHeader.SizeOfImage,
-        ),
-        ImageNtHeaders::X86(_) => bail!("x86 minesweeper not yet supported"),
+pub fn board(context: &ProcessContext) -> Result<Board> {
+    let p_g = locate(context)?;
+    read_board(context, p_g)
+}
+
+/// Finds the address of `G`, the global pointer to the game, by scanning the
+/// image for the Get Singleton pattern. Every candidate match is followed up
+/// to the board, and the first one leading to a sane board is used. Scanning
+/// is costly, so the address is meant to be reused with [`read_board`].
+pub fn locate(context: &ProcessContext) -> Result<*const c_void> {
+    let a_remote = &context.handle;
+    let (image_base, arch) = (context.image_base, context.arch);
+    let pattern: &[Option<u8>] = match arch {
+        Architecture::X64 => &WIN6_SAFE_GET_SINGLETON,
+        Architecture::X86 => &WIN6_SAFE_GET_SINGLETON_X86,
     };
-    trace!("NT Image Base address:  {:?}", image_base);
-    trace!("NT Image size: {:#x}", image_size);
     debug!("Finding game structure in-memory");
-    let get_singleton_instruction_offset =
-        memory::search(&WIN6_SAFE_GET_SINGLETON, &a_remote, image_base, image_size)?
-            .ok_or(anyhow!("Get Singleton pattern not found in-memory"))?;
-    trace!(
-        "Get Singleton at offset {:#x}",
-        get_singleton_instruction_offset
+    let code = code_range(context);
+    let code_base = unsafe { image_base.add(code.start as usize) };
+    let code_size = code.end - code.start;
+    // The code range lies within the image of the process, see code_range
+    let mut candidates: Vec<_> =
+        unsafe { memory::search_all_masked(pattern, a_remote, code_base, code_size) }?
+            .into_iter()
+            .map(|offset| code.start as usize + offset)
+            .collect();
+    // Breakpoints set by an attached debugger replace the first byte of
+    // instructions with int3, which may hide the pattern from the exact search
+    let mut patched = Vec::new();
+    if candidates.is_empty() && context.being_debugged {
+        debug!("Get Singleton pattern not found, retrying with breakpoints over it");
+        let found = unsafe {
+            memory::search_all_patched(
+                pattern,
+                a_remote,
+                code_base,
+                code_size,
+                SOFTWARE_BREAKPOINT,
+                MAX_BREAKPOINTS,
+            )
+        };
+        match found {
+            Ok(offsets) => patched.extend(
+                offsets
+                    .into_iter()
+                    .map(|offset| code.start as usize + offset),
+            ),
+            Err(error) => debug!("Unable to search for breakpoints: {:#}", error),
+        }
+        candidates.extend(&patched);
+    }
+    ensure!(
+        !candidates.is_empty(),
+        Error::PatternNotFound("Get Singleton")
     );
-    let board = unsafe {
-        let p_g_offset =
-            image_base.offset(get_singleton_instruction_offset as isize + OFFS_WIN6_TO_G);
-        let g_offset: u32 = memory::copy(&a_remote, p_g_offset as *const _)?;
-        // if Minesweeper is x64
-        let p_g = p_g_offset.offset(1 + std::mem::size_of::<u32>() as isize + g_offset as isize);
-        trace!("G address: {:?}", p_g);
-        let p_game: *const MinesweeperGame = memory::copy(&a_remote, p_g as *const _)?;
-        trace!("Game address: {:?}", p_game);
-        let game = memory::copy(&a_remote, p_game)?;
-        memory::copy(&a_remote, game.p_board)?
+    let mut last_error = anyhow!(Error::PatternNotFound("Get Singleton"));
+    for &offset in &candidates {
+        trace!("Get Singleton candidate at offset {:#x}", offset);
+        let located = unsafe {
+            resolve_g(a_remote, arch, image_base.add(offset))
+                .and_then(|p_g| board_from_g(a_remote, arch, p_g).map(|_| p_g))
+        };
+        match located {
+            Ok(p_g) => {
+                if !patched.is_empty() {
+                    warn!(
+                        "Get Singleton pattern only found with software breakpoints (0xCC) \
+                        over it, check the breakpoints of the attached debugger"
+                    );
+                }
+                return Ok(p_g);
+            }
+            Err(error) => {
+                debug!(
+                    "Discarding Get Singleton candidate at {:#x}: {:#}",
+                    offset, error
+                );
+                last_error = error;
+            }
+        }
+    }
+    // Matches tolerating breakpoints are only guesses, so failing to follow
+    // them means the pattern was not found after all
+    match patched.is_empty() {
+        true => Err(last_error),
+        false => Err(last_error.context(Error::PatternNotFound("Get Singleton"))),
+    }
+}
+
+/// Returns the RVAs of the image covered by its code sections, where the Get
+/// Singleton pattern is searched for. Not scanning the data sections is both
+/// faster and avoids matching unrelated data. The whole image is covered if
+/// its section table cannot be read or has no code section.
+fn code_range(context: &ProcessContext) -> Range<u32> {
+    let (memory, image_base) = (&context.handle, context.image_base);
+    let sections = unsafe {
+        process::nt_headers(memory, image_base)
+            .and_then(|headers| process::sections(memory, image_base, &headers))
     };
+    let code = match sections {
+        Ok(sections) => sections
+            .iter()
+            .filter(|section| {
+                section.characteristics.0 & (IMAGE_SCN_CNT_CODE.0 | IMAGE_SCN_MEM_EXECUTE.0) != 0
+            })
+            .map(|section| section.virtual_range())
+            .reduce(|all, range| all.start.min(range.start)..all.end.max(range.end)),
+        Err(error) => {
+            debug!("Unable to read the section table: {:#}", error);
+            None
+        }
+    };
+    match code {
+        Some(code) if code.end <= context.image_size => {
+            trace!("Code sections at RVAs {:#x}..{:#x}", code.start, code.end);
+            code
+        }
+        _ => 0..context.image_size,
+    }
+}
+
+/// Reads the board of the game pointed to by `G`, as found by [`locate`].
+/// The game structures are followed again on every read, so new games are
+/// picked up even if their board is allocated elsewhere.
+pub fn read_board(context: &ProcessContext, p_g: *const c_void) -> Result<Board> {
+    let (a_remote, arch) = (&context.handle, context.arch);
+    let (board, (rows, columns)) = unsafe { board_from_g(a_remote, arch, p_g) }?;
     debug!("Parsing data from game board");
-    let mut parsed_board = Board::new(
-        board.cb_rows as usize,
-        board.cb_columns as usize,
-        board.cb_mines,
-    );
+    let mut parsed_board = Board::new(rows, columns, board.cb_mines);
     unsafe {
         parse_raw_board(
-            &a_remote,
+            a_remote,
+            arch,
             &mut parsed_board,
             board.ref_visibles,
             Visibility::Revealed,
         )
         .context("Unexpected error parsing visible fields")?;
         parse_raw_board(
-            &a_remote,
+            a_remote,
+            arch,
             &mut parsed_board,
             board.ref_mines,
             Visibility::Hidden,
         )
         .context("Unexpected error parsing mine fields")?;
     }
+    // The phase of the game has not been identified among the unknown board
+    // fields, but a game with every safe cell opened can only have been won.
+    // In-progress and lost games look alike otherwise, so both are left
+    // without a status
+    let summary = parsed_board.summary();
+    if summary.revealed + summary.mines as usize == summary.rows * summary.columns {
+        parsed_board.status = Some(GameStatus::Won);
+    }
+    trace!("Status: {:?}", parsed_board.status);
     Ok(parsed_board)
 }
 
+/// Follows the game structures from `G` up to the board, returning it along
+/// with its validated dimensions. Fails if any structure cannot be read or
+/// the board does not look like a sane one, so that false positives of the
+/// Get Singleton pattern can be told apart.
+unsafe fn board_from_g(
+    memory: &MemoryHandle,
+    arch: Architecture,
+    p_g: *const c_void,
+) -> Result<(BoardInfo, (usize, usize))> {
+    trace!("G address: {:?}", p_g);
+    process::record_address("G", p_g);
+    let p_game = read_pointer(memory, arch, p_g)?;
+    trace!("Game address: {:?}", p_game);
+    process::record_address("Game", p_game);
+    let game_size = match arch {
+        Architecture::X64 => mem::size_of::<MinesweeperGame>(),
+        Architecture::X86 => mem::size_of::<MinesweeperGame32>(),
+    };
+    ensure!(
+        memory::is_readable(memory, p_game, game_size),
+        "game pointer {:?} does not point to readable memory",
+        p_game
+    );
+    let p_board = match arch {
+        Architecture::X64 => {
+            memory::copy(memory, p_game as *const MinesweeperGame)?.p_board as *const c_void
+        }
+        Architecture::X86 => {
+            memory::copy(memory, p_game as *const MinesweeperGame32)?.p_board as *const c_void
+        }
+    };
+    process::record_address("Board", p_board);
+    let board_size = match arch {
+        Architecture::X64 => mem::size_of::<MinesweeperBoard>(),
+        Architecture::X86 => mem::size_of::<MinesweeperBoard32>(),
+    };
+    ensure!(
+        memory::is_readable(memory, p_board, board_size),
+        "board pointer {:?} does not point to readable memory",
+        p_board
+    );
+    let board = board_info(memory, arch, p_board)?;
+    let (rows, columns) = dimensions(memory, arch, &board)?;
+    ensure!(
+        (1..=MAX_BOARD_SIDE).contains(&rows) && (1..=MAX_BOARD_SIDE).contains(&columns),
+        "implausible board of {} r x {} c",
+        rows,
+        columns
+    );
+    Ok((board, (rows, columns)))
+}
+
+/// Reads a pointer of the size used by the given architecture.
+unsafe fn read_pointer(
+    memory: &MemoryHandle,
+    arch: Architecture,
+    p: *const c_void,
+) -> Result<*const c_void> {
+    Ok(match arch {
+        Architecture::X64 => memory::copy(memory, p as *const u64)? as *const c_void,
+        Architecture::X86 => memory::copy(memory, p as *const u32)? as *const c_void,
+    })
+}
+
+/// Reads an array of pointers of the size used by the given architecture.
+unsafe fn read_pointers(
+    memory: &MemoryHandle,
+    arch: Architecture,
+    p: *const c_void,
+    count: usize,
+) -> Result<Vec<*const c_void>> {
+    Ok(match arch {
+        Architecture::X64 => memory::copy_array(memory, p as *const u64, count)?
+            .into_iter()
+            .map(|pointer| pointer as *const c_void)
+            .collect(),
+        Architecture::X86 => memory::copy_array(memory, p as *const u32, count)?
+            .into_iter()
+            .map(|pointer| pointer as *const c_void)
+            .collect(),
+    })
+}
+
+unsafe fn element(memory: &MemoryHandle, arch: Architecture, p: *const c_void) -> Result<Element> {
+    Ok(match arch {
+        Architecture::X64 => {
+            let element = memory::copy(memory, p as *const MinesweeperElement)?;
+            Element {
+                cb_elements: element.cb_elements,
+                elements: element.elements,
+            }
+        }
+        Architecture::X86 => {
+            let element = memory::copy(memory, p as *const MinesweeperElement32)?;
+            Element {
+                cb_elements: element.cb_elements,
+                elements: element.elements as *const c_void,
+            }
+        }
+    })
+}
+
+unsafe fn board_info(
+    memory: &MemoryHandle,
+    arch: Architecture,
+    p: *const c_void,
+) -> Result<BoardInfo> {
+    Ok(match arch {
+        Architecture::X64 => {
+            let board = memory::copy(memory, p as *const MinesweeperBoard)?;
+            BoardInfo {
+                cb_mines: board.cb_mines,
+                cb_rows: board.cb_rows,
+                cb_columns: board.cb_columns,
+                ref_visibles: board.ref_visibles as *const c_void,
+                ref_mines: board.ref_mines as *const c_void,
+            }
+        }
+        Architecture::X86 => {
+            let board = memory::copy(memory, p as *const MinesweeperBoard32)?;
+            BoardInfo {
+                cb_mines: board.cb_mines,
+                cb_rows: board.cb_rows,
+                cb_columns: board.cb_columns,
+                ref_visibles: board.ref_visibles as *const c_void,
+                ref_mines: board.ref_mines as *const c_void,
+            }
+        }
+    })
+}
+
+/// Returns the `(rows, columns)` dimensions of the board, checked against the
+/// element arrays actually holding the cells. Some builds are known to store
+/// the row and column counts the other way around, in which case the swapped
+/// interpretation is used instead of reading a transposed board.
+unsafe fn dimensions(
+    memory: &MemoryHandle,
+    arch: Architecture,
+    board: &BoardInfo,
+) -> Result<(usize, usize)> {
+    let declared = (board.cb_rows as usize, board.cb_columns as usize);
+    let root_element =
+        element(memory, arch, board.ref_visibles).context("failed to retrieve root element")?;
+    let columns = root_element.cb_elements as usize;
+    let columns_data = read_pointers(memory, arch, root_element.elements, columns)
+        .context("failed to retrieve column pointers")?;
+    let Some(&column) = columns_data.iter().find(|column| !column.is_null()) else {
+        trace!("No column available to check the board dimensions");
+        return Ok(declared);
+    };
+    let rows = element(memory, arch, column)
+        .context("failed to retrieve column data")?
+        .cb_elements as usize;
+    let dimensions = if (rows, columns) == declared {
+        declared
+    } else if (columns, rows) == declared {
+        warn!(
+            "Row and column counts are swapped in this build, reading {} r x {} c",
+            rows, columns
+        );
+        (rows, columns)
+    } else {
+        bail!(
+            "board declares {} r x {} c, but holds {} r x {} c",
+            declared.0,
+            declared.1,
+            rows,
+            columns
+        );
+    };
+    ensure!(
+        (board.cb_mines as usize) < dimensions.0 * dimensions.1,
+        "{} mines do not fit in a {} r x {} c board",
+        board.cb_mines,
+        dimensions.0,
+        dimensions.1
+    );
+    Ok(dimensions)
+}
+
+/// Resolves the address of `G` by decoding the closest instruction accessing
+/// it before the Get Singleton pattern: RIP-relative on x64, absolute on x86.
+/// Decoding the displacement, instead of assuming a fixed layout, copes with
+/// builds (such as CFG-enabled ones) where the surrounding instructions are
+/// shifted by a few bytes.
+unsafe fn resolve_g(
+    memory: &MemoryHandle,
+    arch: Architecture,
+    p_pattern: *const c_void,
+) -> Result<*const c_void> {
+    let p_window = p_pattern.sub(G_ACCESS_WINDOW);
+    let window: Vec<u8> = memory::copy_array(memory, p_window as *const _, G_ACCESS_WINDOW)
+        .context("failed to copy instructions before the Get Singleton pattern")?;
+    let accesses: &[(&[u8], &[u8])] = match arch {
+        Architecture::X64 => &G_ACCESSES,
+        Architecture::X86 => &G_ACCESSES_X86,
+    };
+    for &(opcode, immediate) in accesses {
+        let length = opcode.len() + mem::size_of::<i32>() + immediate.len();
+        let Some(start) = (0..=G_ACCESS_WINDOW - length).rev().find(|&i| {
+            window[i..].starts_with(opcode)
+                && window[i + length - immediate.len()..].starts_with(immediate)
+        }) else {
+            continue;
+        };
+        let displacement = &window[start + opcode.len()..][..mem::size_of::<i32>()];
+        if displacement.contains(&SOFTWARE_BREAKPOINT) {
+            warn!(
+                "G displacement may be overwritten by a software breakpoint: {displacement:02x?}"
+            );
+        }
+        let displacement = i32::from_le_bytes(displacement.try_into()?);
+        trace!(
+            "G accessed at {:?} with displacement {:#x}",
+            p_window.add(start),
+            displacement
+        );
+        return Ok(match arch {
+            Architecture::X64 => p_window.add(start + length).offset(displacement as isize),
+            Architecture::X86 => displacement as u32 as *const c_void,
+        });
+    }
+    bail!("no access to G found before the Get Singleton pattern")
+}
+
 unsafe fn parse_raw_board(
     memory: &MemoryHandle,
+    arch: Architecture,
     board: &mut Board,
-    base: *const MinesweeperElement,
+    base: *const c_void,
     visible: Visibility,
 ) -> Result<()> {
-    let root_element = memory::copy(memory, base).context("failed to retrieve root element")?;
+    let root_element = element(memory, arch, base).context("failed to retrieve root element")?;
     let columns = root_element.cb_elements as usize;
-    let columns_data: Vec<PMinesweeperElement> =
-        memory::copy_array(memory, root_element.elements as *const _, columns)
-            .context("failed to retrieve column pointers")?;
-    for (c, column) in columns_data.iter().enumerate() {
-        let column = memory::copy(memory, column.0 as *const MinesweeperElement)
-            .context("failed to retrieve column data")?;
+    let columns_data = read_pointers(memory, arch, root_element.elements, columns)
+        .context("failed to retrieve column pointers")?;
+    for (c, &column) in columns_data.iter().enumerate() {
+        if column.is_null() {
+            // Boards still being built in-memory may lack some columns, which
+            // are left as hidden cells
+            trace!("Skipping column {c}: null pointer");
+            continue;
+        }
+        let column = element(memory, arch, column).context("failed to retrieve column data")?;
         let rows = column.cb_elements as usize;
         match visible {
             Visibility::Revealed => {
                 let rows_data = memory::copy_array(memory, column.elements as *const u32, rows)
                     .context(format!("failed to retrieve rows from column {c}"))?;
-                for (r, row) in rows_data.iter().enumerate() {
-                    board.insert(&DISP_MINESWEEPER[*row as usize], r, c)?;
+                for (r, &row) in rows_data.iter().enumerate() {
+                    let cell = DISP_MINESWEEPER.get(row as usize).with_context(|| {
+                        format!("unexpected value {row} of the cell at row {r}, column {c}")
+                    })?;
+                    board.insert(*cell, r, c)?;
                 }
             }
             Visibility::Hidden => {
@@ -182,7 +628,7 @@ unsafe fn parse_raw_board(
                     .context(format!("failed to retrieve rows from column {c}"))?;
                 for (r, row) in rows_data.iter().enumerate() {
                     if *row != 0 {
-                        board.insert(&"*".bright_red(), r, c)?;
+                        board.insert(Cell::Mine, r, c)?;
                     }
                 }
             }
@@ -2,37 +2,73 @@
 //! Windows XP version of Minesweeper.
 
 use crate::memory::{self, MemoryHandle};
-use crate::Board;
+use crate::process::{self, ProcessContext};
+use crate::{Board, Cell, GameOptions, GameStatus, Timer};
 
-use anyhow::{ensure, Result};
-use colored::*;
-use lazy_static::lazy_static;
-use log::{debug, trace};
+use anyhow::{ensure, Context, Result};
+use log::{debug, trace, warn};
+use std::{mem, ops::RangeInclusive, path::Path};
 
+/// Class name of the main game window. Unlike the window title, it is the
+/// same on every localized release of the game.
+pub const WINDOW_CLASS: &str = "Minesweeper";
+
+/// Address of the board when the image is loaded at its preferred base. Like
+/// the rest of the addresses of game structures, it is relocated to the actual
+/// base before being read, see [`Relocation`].
 const WINXP_BOARD_ADDRESS: u32 = 0x01005330;
 const WINXP_BOARD_SIZE: usize = 0x360;
 const FIELD_SIZE: usize = 0x20;
 const CELL_DELIMITER: u8 = 0x10;
+/// Board widths allowed by the game, which clamps custom sizes to them. Each
+/// row is stored in [`FIELD_SIZE`] bytes, including a border cell on both
+/// sides, so wider boards do not fit.
+const WINXP_WIDTH_RANGE: RangeInclusive<u32> = 9..=30;
+/// Board heights allowed by the game. Rows are stored one after the other
+/// between a border row above and below, within [`WINXP_BOARD_SIZE`] bytes.
+const WINXP_HEIGHT_RANGE: RangeInclusive<u32> = 9..=24;
 const CELL_EMPTY: u8 = 0x0f;
+/// Bit of the cells holding a mine.
+const CELL_MINE: u8 = 0x80;
+/// Displayed state of flagged cells.
+const CELL_FLAG: u8 = 0x0e;
+/// Displayed state of the mines shown once a game is lost.
+const CELL_MINE_SHOWN: u8 = 0x0a;
+/// Address of the `fSound` and `fMark` fields of the game preferences, which
+/// are stored next to each other as 32-bit booleans.
+const WINXP_OPTIONS_ADDRESS: u32 = 0x010056b8;
+/// Address of `fTimer`, set while the timer is counting.
+const WINXP_TIMER_RUNNING_ADDRESS: u32 = 0x01005164;
+/// Address of `cSec`, the number of seconds shown by the timer.
+const WINXP_TIMER_SECONDS_ADDRESS: u32 = 0x0100579c;
+/// Largest value shown by the timer, which stops counting once reached.
+const WINXP_TIMER_MAX: u32 = 999;
+/// Address of `fStatus`, holding the state flags of the game.
+const WINXP_STATUS_ADDRESS: u32 = 0x01005160;
+/// Flag of `fStatus` set while a game is in progress. It is cleared once the
+/// game is over, whether won or lost.
+const STATUS_PLAYING: u32 = 0x01;
+/// Cell value of the mine opened by the player, which lost the game.
+const CELL_BLASTED: u8 = 0xcc;
+/// Address of `cBombLeft`, the number of mines shown by the mine counter.
+const WINXP_MINE_COUNTER_ADDRESS: u32 = 0x01005194;
 
-lazy_static! {
-    static ref DISP_MINESWEEPER: Vec<ColoredString> = vec![
-        " ".into(),
-        "1".blue(),
-        "2".green(),
-        "3".red(),
-        "4".purple(),
-        "5".truecolor(94, 9, 28),
-        "6".cyan(),
-        "7".bright_blue(),
-        "8".bright_green(),
-        ".".into(),
-        "*".bright_red(),
-        "F".on_red(),
-        "?".black().on_white(),
-        "!".red().bold(),
-    ];
-}
+const DISP_MINESWEEPER: [Cell; 14] = [
+    Cell::Empty,
+    Cell::Number(1),
+    Cell::Number(2),
+    Cell::Number(3),
+    Cell::Number(4),
+    Cell::Number(5),
+    Cell::Number(6),
+    Cell::Number(7),
+    Cell::Number(8),
+    Cell::Hidden,
+    Cell::Mine,
+    Cell::Flag,
+    Cell::Question,
+    Cell::Unknown,
+];
 
 enum Element {
     Hidden = 9,
@@ -42,6 +78,12 @@ enum Element {
     Unknown = 13,
 }
 
+#[repr(C)]
+struct MinesweeperOptions {
+    sound: u32,
+    mark: u32,
+}
+
 #[repr(C)]
 struct MinesweeperBoard {
     mines: u32,
@@ -51,26 +93,146 @@ struct MinesweeperBoard {
     data: [u8; WINXP_BOARD_SIZE],
 }
 
+/// Difference between the address the image is loaded at and its preferred
+/// base, taken from the optional header of the image on disk instead of
+/// assumed to be `0x01000000`.
+/// The image may be relocated when ASLR is forced on it.
+pub struct Relocation {
+    delta: u64,
+}
+
+/// Preferred base of the image of every known build of the game, used when
+/// the image cannot be read from disk.
+const WINXP_PREFERRED_BASE: u64 = 0x01000000;
+
+impl Relocation {
+    /// Computes the relocation of the image of the given game process.
+    ///
+    /// The preferred base is read from the image on disk, since the loader
+    /// rewrites the `ImageBase` of the headers mapped in the process with the
+    /// actual base when relocating it.
+    pub fn new(context: &ProcessContext) -> Result<Relocation> {
+        let preferred = preferred_base(context).unwrap_or_else(|error| {
+            debug!(
+                "Unable to read the image on disk, assuming it prefers {:#x}: {:#}",
+                WINXP_PREFERRED_BASE, error
+            );
+            WINXP_PREFERRED_BASE
+        });
+        let delta = (context.image_base as u64).wrapping_sub(preferred);
+        if delta != 0 {
+            debug!(
+                "Image relocated from {:#x} to {:?}",
+                preferred, context.image_base
+            );
+        }
+        Ok(Relocation { delta })
+    }
+
+    /// Returns the actual address of a fixed address of the game.
+    fn address<T>(&self, address: u32) -> *const T {
+        (address as u64).wrapping_add(self.delta) as *const T
+    }
+}
+
+/// Reads the preferred base of the main image of the process from its file,
+/// found through the modules loaded in the process.
+fn preferred_base(context: &ProcessContext) -> Result<u64> {
+    let module = process::modules(&context.handle)?
+        .into_iter()
+        .find(|module| module.base == context.image_base)
+        .context("main image not found among the loaded modules")?;
+    let image = MemoryHandle::open_image(Path::new(&module.path))?;
+    match &image {
+        MemoryHandle::File(file) => file.image_base(),
+        _ => None,
+    }
+    .map(|base| base as u64)
+    .context("image opened without a preferred base")
+}
+
+/// Reads the board structure of the game, checking that its dimensions and
+/// borders are those of a valid board.
+fn read_raw_board(context: &ProcessContext, relocation: &Relocation) -> Result<MinesweeperBoard> {
+    let p_board = relocation.address(WINXP_BOARD_ADDRESS);
+    process::record_address("Board", p_board as *const _);
+    let board: MinesweeperBoard = unsafe { memory::copy(&context.handle, p_board) }?;
+    ensure!(
+        WINXP_WIDTH_RANGE.contains(&board.width),
+        "invalid board width {}, expected {}-{}",
+        board.width,
+        WINXP_WIDTH_RANGE.start(),
+        WINXP_WIDTH_RANGE.end()
+    );
+    ensure!(
+        WINXP_HEIGHT_RANGE.contains(&board.height),
+        "invalid board height {}, expected {}-{}",
+        board.height,
+        WINXP_HEIGHT_RANGE.start(),
+        WINXP_HEIGHT_RANGE.end()
+    );
+    trace!("Board: {} c x {} r", board.width, board.height);
+    let (header, empty) = board.data[..FIELD_SIZE].split_at((board.width + 2) as _);
+    ensure!(
+        header.iter().all(|&n| n == CELL_DELIMITER) && empty.iter().all(|&n| n == CELL_EMPTY),
+        "invalid board structure",
+    );
+    Ok(board)
+}
+
+/// Shows every mine of the board in the game window, as it does once a game
+/// is lost, returning the number of cells changed. Flagged mines are left
+/// untouched. Unlike the rest of the crate, this modifies the game memory, so
+/// the process must have been opened with write access, see
+/// [`ProcessContext::open_writable`].
+///
+/// Only the displayed state of the cells is changed, so the game carries on
+/// as before. The window only shows the mines once it is next repainted, for
+/// instance after being minimized and restored.
+pub fn reveal_mines(context: &ProcessContext) -> Result<usize> {
+    let relocation = Relocation::new(context)?;
+    let board = read_raw_board(context, &relocation)?;
+    // The cells follow the header fields of the board structure
+    let p_data = relocation.address::<u8>(WINXP_BOARD_ADDRESS) as usize
+        + (mem::size_of::<MinesweeperBoard>() - WINXP_BOARD_SIZE);
+    let mut revealed = Vec::new();
+    for r in 1..=board.height as usize {
+        for c in 1..=board.width as usize {
+            let index = r * FIELD_SIZE + c;
+            let cell = board.data[index];
+            if cell & CELL_MINE == 0 || cell == CELL_BLASTED || cell & 0x0f == CELL_FLAG {
+                continue;
+            }
+            let shown = (cell & 0xf0) | CELL_MINE_SHOWN;
+            unsafe { memory::write(&context.handle, (p_data + index) as *mut u8, &shown) }
+                .with_context(|| format!("failed to reveal the mine at row {r}, column {c}"))?;
+            revealed.push((index, shown));
+        }
+    }
+    // Read the board back, in case the game overwrote any change meanwhile
+    let board = read_raw_board(context, &relocation)?;
+    ensure!(
+        revealed
+            .iter()
+            .all(|&(index, shown)| board.data[index] == shown),
+        "the game changed the board while revealing the mines"
+    );
+    debug!("Revealed {} mines", revealed.len());
+    Ok(revealed.len())
+}
+
 /// Retrieve the board state from the provided process.
-pub fn board(a_remote: MemoryHandle) -> Result<Board> {
+pub fn board(context: &ProcessContext) -> Result<Board> {
+    read_board(context, &Relocation::new(context)?)
+}
+
+/// Same as [`board`], reusing a [`Relocation`] computed beforehand.
+pub fn read_board(context: &ProcessContext, relocation: &Relocation) -> Result<Board> {
+    let a_remote = &context.handle;
     debug!("Reading game board state");
-    let board = unsafe {
-        let p_board = WINXP_BOARD_ADDRESS as *const _;
-        let board: MinesweeperBoard = memory::copy(&a_remote, p_board)?;
-        ensure!(9 <= board.width && board.width <= 30, "invalid board width");
-        ensure!(
-            9 <= board.height && board.height <= 24,
-            "invalid board height"
-        );
-        trace!("Board: {} c x {} r", board.width, board.height);
-        let (header, empty) = board.data[..FIELD_SIZE].split_at((board.width + 2) as _);
-        ensure!(
-            header.iter().all(|&n| n == CELL_DELIMITER) && empty.iter().all(|&n| n == CELL_EMPTY),
-            "invalid board structure",
-        );
-        board
-    };
+    let board = read_raw_board(context, relocation)?;
     let mut parsed_board = Board::new(board.height as _, board.width as _, board.mines);
+    let mut blasted = false;
 
     for (r, data) in board
         .data
@@ -81,16 +243,88 @@ pub fn board(a_remote: MemoryHandle) -> Result<Board> {
         .enumerate()
     {
         for (c, cell) in data.iter().enumerate() {
+            blasted |= *cell == CELL_BLASTED;
             let value = match cell {
-                _ if cell & 0x80 != 0 || *cell == 0xcc => &DISP_MINESWEEPER[Element::Mine as usize],
-                _ if cell & 0x0f == 0x0e => &DISP_MINESWEEPER[Element::Flag as usize],
-                _ if cell & 0x0f == 0x0d => &DISP_MINESWEEPER[Element::Mark as usize],
-                _ if cell & 0xf0 == 0 => &DISP_MINESWEEPER[Element::Hidden as usize],
-                _ if cell & 0x40 != 0 => &DISP_MINESWEEPER[(cell & 0x0f) as usize],
-                _ => &DISP_MINESWEEPER[Element::Unknown as usize],
+                _ if cell & 0x80 != 0 || *cell == CELL_BLASTED => {
+                    DISP_MINESWEEPER[Element::Mine as usize]
+                }
+                _ if cell & 0x0f == 0x0e => DISP_MINESWEEPER[Element::Flag as usize],
+                _ if cell & 0x0f == 0x0d => DISP_MINESWEEPER[Element::Mark as usize],
+                _ if cell & 0xf0 == 0 => DISP_MINESWEEPER[Element::Hidden as usize],
+                _ if cell & 0x40 != 0 => *DISP_MINESWEEPER
+                    .get((cell & 0x0f) as usize)
+                    .with_context(|| {
+                        format!("unexpected value {cell:#04x} of the cell at row {r}, column {c}")
+                    })?,
+                _ => DISP_MINESWEEPER[Element::Unknown as usize],
             };
-            parsed_board.insert(value, r, c).unwrap();
+            parsed_board
+                .insert(value, r, c)
+                .with_context(|| format!("failed to place the cell at row {r}, column {c}"))?;
         }
     }
+
+    let options: MinesweeperOptions =
+        unsafe { memory::copy(a_remote, relocation.address(WINXP_OPTIONS_ADDRESS)) }
+            .context("failed to read game options")?;
+    let options = GameOptions {
+        sound: options.sound != 0,
+        marks: options.mark != 0,
+    };
+    trace!("Options: {:?}", options);
+    // Question marks can only be placed with marks enabled, although the
+    // ones placed before disabling them are kept on the board
+    let marked = parsed_board
+        .iter()
+        .any(|(_, _, cell)| *cell == Cell::Question);
+    if marked && !options.marks {
+        warn!("Marked cells found with marks disabled, the board may be misread");
+    }
+    parsed_board.options = Some(options);
+
+    let (running, seconds): (u32, u32) = unsafe {
+        (
+            memory::copy(a_remote, relocation.address(WINXP_TIMER_RUNNING_ADDRESS))
+                .context("failed to read timer state")?,
+            memory::copy(a_remote, relocation.address(WINXP_TIMER_SECONDS_ADDRESS))
+                .context("failed to read timer value")?,
+        )
+    };
+    // The timer stays at zero until the first click, and freezes once the
+    // game is over, so the displayed value is only meaningful in context
+    let timer = match (running != 0, seconds) {
+        (true, seconds) => Timer::Running(seconds),
+        (false, 0) if parsed_board.summary().revealed == 0 => Timer::NotStarted,
+        (false, seconds) => Timer::Stopped(seconds),
+    };
+    trace!("Timer: {:?}", timer);
+    if seconds <= WINXP_TIMER_MAX {
+        parsed_board.timer = Some(timer);
+    } else {
+        warn!("Ignoring implausible timer value of {} seconds", seconds);
+    }
+
+    let mine_counter: i32 =
+        unsafe { memory::copy(a_remote, relocation.address(WINXP_MINE_COUNTER_ADDRESS)) }
+            .context("failed to read mine counter")?;
+    trace!("Mine counter: {}", mine_counter);
+    parsed_board.mine_counter = Some(mine_counter);
+
+    let status: u32 = unsafe { memory::copy(a_remote, relocation.address(WINXP_STATUS_ADDRESS)) }
+        .context("failed to read game status")?;
+    // Once the game is over, telling a win from a loss takes the board: only
+    // lost games have a blasted mine, and only won ones have every safe cell
+    // opened. Anything else, such as transient states, is left unknown
+    let summary = parsed_board.summary();
+    let status = match status {
+        _ if status & STATUS_PLAYING != 0 => GameStatus::Playing,
+        _ if blasted => GameStatus::Lost,
+        _ if summary.revealed + board.mines as usize == summary.rows * summary.columns => {
+            GameStatus::Won
+        }
+        _ => GameStatus::Unknown,
+    };
+    trace!("Status: {:?}", status);
+    parsed_board.status = Some(status);
     Ok(parsed_board)
 }